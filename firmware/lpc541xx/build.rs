@@ -5,21 +5,257 @@ fn main() -> Result<(), Box<dyn Error>> {
     #[allow(dead_code)]
     const SLAVE: &str = "thumbv6m-none-eabi";
 
+    // NOTE `rerun-if-env-changed` disables the default of re-running on any change to the package
+    println!("cargo:rerun-if-changed=build.rs");
+
     let target = env::var("TARGET")?;
     let out_dir = PathBuf::from(env::var("OUT_DIR")?);
-    let name = env::var("CARGO_PKG_NAME")?;
+    let part = Part::from_features()?;
+    // parts without a Cortex-M0+ can only run single-core applications
+    let single_core = env::var_os("CARGO_FEATURE_SINGLE_CORE").is_some() || !part.dual_core;
+
+    if !part.dual_core && target != MASTER {
+        return Err(format!(
+            "the {} has no Cortex-M0+; use the {} target",
+            part.name, MASTER
+        )
+        .into());
+    }
+
+    if single_core && target != MASTER {
+        return Err(format!("the `single-core` feature requires the {} target", MASTER).into());
+    }
+
+    if !part.dual_core {
+        println!("cargo:rustc-cfg=no_cm0plus");
+    }
+
+    if single_core {
+        // plain `cargo build`; there's no `cargo microamp` to pass the linker script
+        // NOTE `rustc-link-arg` only applies to the examples of this crate; dependent applications
+        // must pass `-C link-arg=-Tsingle.x` themselves. `rustc-link-search` does reach them, so
+        // `single.x` and `memory.x` are found
+        println!("cargo:rustc-cfg=single_core");
+        println!("cargo:rustc-link-arg=-Tsingle.x");
+        println!(
+            "cargo:rustc-link-search={}",
+            env::var("CARGO_MANIFEST_DIR")?
+        );
+        println!("cargo:rerun-if-changed=single.x");
+
+        fs::write(out_dir.join("memory.x"), part.single_memory())?;
+    } else {
+        fs::write(out_dir.join("memory.x"), part.memory()?)?;
+    }
+    println!("cargo:rustc-link-search={}", out_dir.display());
 
     if target == MASTER {
         println!("cargo:rustc-cfg=master");
+    }
 
-        fs::copy(
-            format!("bin/{}.a", target),
-            out_dir.join(format!("lib{}.a", name)),
-        )?;
+    Ok(())
+}
+
+// SRAM0, SRAM1 and SRAM2 are contiguous and start here
+const SRAM_START: u32 = 0x2000_0000;
+// size, in KiB, of the SRAM0 bank; it's the only bank that has a clock at reset
+const SRAM_BANK: u32 = 64;
+
+// A part number; all sizes are in KiB
+struct Part {
+    name: &'static str,
+    flash: u32,
+    // SRAM0 + SRAM1 + SRAM2; SRAMX (32 KiB) is present on all parts
+    sram: u32,
+    dual_core: bool,
+    // default sizes of SRAM0 and SRAM1
+    sram0: u32,
+    sram1: u32,
+}
 
-        println!("cargo:rustc-link-lib=static={}", name);
-        println!("cargo:rustc-link-search={}", out_dir.display());
+impl Part {
+    const ALL: &'static [Part] = &[
+        Part {
+            name: "LPC54113J128",
+            flash: 128,
+            sram: 64,
+            dual_core: false,
+            sram0: 0,
+            sram1: 0,
+        },
+        Part {
+            name: "LPC54113J256",
+            flash: 256,
+            sram: 160,
+            dual_core: false,
+            sram0: 0,
+            sram1: 0,
+        },
+        Part {
+            name: "LPC54114J128",
+            flash: 128,
+            sram: 64,
+            dual_core: true,
+            sram0: 32,
+            sram1: 16,
+        },
+        Part {
+            name: "LPC54114J256",
+            flash: 256,
+            sram: 160,
+            dual_core: true,
+            sram0: 64,
+            sram1: 64,
+        },
+    ];
+
+    // the part selected with the `lpc5411*` Cargo features
+    fn from_features() -> Result<&'static Part, Box<dyn Error>> {
+        let mut parts = Part::ALL
+            .iter()
+            .filter(|part| env::var_os(format!("CARGO_FEATURE_{}", part.name)).is_some());
+
+        match (parts.next(), parts.next()) {
+            (Some(part), None) => Ok(part),
+            (None, _) => Err("a part number feature, e.g. `lpc54114j256`, must be enabled".into()),
+            (Some(_), Some(_)) => Err(
+                "only one part number feature can be enabled; disable the default features to \
+                 select a part other than the LPC54114J256"
+                    .into(),
+            ),
+        }
     }
 
-    Ok(())
+    // Generates `memory.x` from the `LPC541XX_*` environment variables
+    fn memory(&self) -> Result<String, Box<dyn Error>> {
+        // Flash is erased in sectors of 32 KiB
+        const FLASH_SECTOR: u32 = 32;
+        let (flash, sram) = (self.flash, self.sram);
+
+        // Flash of core #1
+        let flash1 = size("LPC541XX_FLASH1", 32)?;
+        // RAM of core #0 and core #1; the rest is shared
+        let sram0 = size("LPC541XX_SRAM0", self.sram0)?;
+        let sram1 = size("LPC541XX_SRAM1", self.sram1)?;
+        // run core #1 from SRAMX instead of SRAM1
+        let sramx = flag("LPC541XX_CORE1_SRAMX")?;
+
+        if flash1 == 0 || flash1 >= flash || flash1 % FLASH_SECTOR != 0 {
+            return Err(format!(
+                "LPC541XX_FLASH1 must be a non-zero multiple of {} KiB smaller than {} KiB",
+                FLASH_SECTOR, flash
+            )
+            .into());
+        }
+
+        if sram0 == 0 || sram1 == 0 || sram0 + sram1 >= sram {
+            return Err(format!(
+                "LPC541XX_SRAM0 and LPC541XX_SRAM1 must be non-zero and leave room for shared \
+                 RAM; the {} has {} KiB of RAM",
+                self.name, sram
+            )
+            .into());
+        }
+
+        // core #0 uses the stack, at the end of SRAM0, before it enables the clocks of the other
+        // SRAM banks
+        if sram0 > SRAM_BANK {
+            return Err(format!(
+                "LPC541XX_SRAM0 must not be larger than the {} KiB SRAM0 bank; \
+                 the rest of the SRAM has no clock at reset",
+                SRAM_BANK
+            )
+            .into());
+        }
+
+        let flash0 = flash - flash1;
+        let sram2 = sram - sram0 - sram1;
+
+        Ok(format!(
+            "/* generated by `build.rs` for the {}; see the `LPC541XX_*` environment variables */
+MEMORY
+{{
+  /* NOTE FLASH is connected to a single bus */
+  /* for core #0 */
+  FLASH0 : ORIGIN = 0x{:08x}, LENGTH = {}K
+  /* last sectors for core #1 */
+  FLASH1 : ORIGIN = 0x{:08x}, LENGTH = {}K
+
+  /* NOTE each 64 KiB SRAM bank is connected to a different AHB bus; regions that straddle two banks
+     contend with the other core for the bus */
+  /* for core #0 */
+  SRAM0  : ORIGIN = 0x{:08x}, LENGTH = {}K
+  /* for core #1 */
+  SRAM1  : ORIGIN = 0x{:08x}, LENGTH = {}K
+  /* for `#[shared]` variables */
+  SRAM2  : ORIGIN = 0x{:08x}, LENGTH = {}K
+  SRAMX  : ORIGIN = 0x04000000, LENGTH = 32K
+}}
+
+/* where the image of core #1 (vectors, code and `static` variables) is copied to */
+REGION_ALIAS(\"IMAGE1\", {});
+/* where the `#[ramfunc]` functions of core #0 are copied to */
+REGION_ALIAS(\"RAMFUNC\", {});
+
+ASSERT(ORIGIN(FLASH0) + LENGTH(FLASH0) <= ORIGIN(FLASH1), \"FLASH0 and FLASH1 overlap\");
+ASSERT(ORIGIN(SRAM0) + LENGTH(SRAM0) <= ORIGIN(SRAM1), \"SRAM0 and SRAM1 overlap\");
+ASSERT(ORIGIN(SRAM1) + LENGTH(SRAM1) <= ORIGIN(SRAM2), \"SRAM1 and SRAM2 overlap\");
+",
+            self.name,
+            0,
+            flash0,
+            flash0 * 1024,
+            flash1,
+            SRAM_START,
+            sram0,
+            SRAM_START + sram0 * 1024,
+            sram1,
+            SRAM_START + (sram0 + sram1) * 1024,
+            sram2,
+            if sramx { "SRAMX" } else { "SRAM1" },
+            if sramx { "SRAM0" } else { "SRAMX" },
+        ))
+    }
+
+    // Generates the `memory.x` of single-core applications; see `single.x`
+    fn single_memory(&self) -> String {
+        format!(
+            "/* generated by `build.rs` for the {} */
+MEMORY
+{{
+  /* FLASH0 + FLASH1 */
+  FLASH : ORIGIN = 0x00000000, LENGTH = {}K
+
+  /* the SRAM banks of this part, from SRAM0; these are contiguous */
+  RAM : ORIGIN = 0x{:08x}, LENGTH = {}K
+
+  SRAMX : ORIGIN = 0x04000000, LENGTH = 32K
+}}
+",
+            self.name, self.flash, SRAM_START, self.sram,
+        )
+    }
+}
+
+// reads a size, in KiB, from the environment variable `var`
+fn size(var: &str, default: u32) -> Result<u32, Box<dyn Error>> {
+    println!("cargo:rerun-if-env-changed={}", var);
+
+    match env::var(var) {
+        Ok(s) => s
+            .parse()
+            .map_err(|_| format!("{} must be a size in KiB; got {:?}", var, s).into()),
+        Err(_) => Ok(default),
+    }
+}
+
+// reads a boolean, `0` or `1`, from the environment variable `var`
+fn flag(var: &str) -> Result<bool, Box<dyn Error>> {
+    println!("cargo:rerun-if-env-changed={}", var);
+
+    match env::var(var).as_ref().map(|s| &s[..]) {
+        Ok("1") => Ok(true),
+        Ok("0") | Err(_) => Ok(false),
+        Ok(s) => Err(format!("{} must be `0` or `1`; got {:?}", var, s).into()),
+    }
 }
@@ -5,6 +5,14 @@ EXTERN(VECTORS);
 
 _stack_top = ORIGIN(SRAM0) + LENGTH(SRAM0);
 
+/* memory of core #1; used to validate its image before booting it */
+_sflash1 = ORIGIN(FLASH1);
+_eflash1 = ORIGIN(FLASH1) + LENGTH(FLASH1);
+_ssram1 = ORIGIN(SRAM1);
+_esram1 = ORIGIN(SRAM1) + LENGTH(SRAM1);
+_simage1 = ORIGIN(IMAGE1);
+_eimage1 = ORIGIN(IMAGE1) + LENGTH(IMAGE1);
+
 SECTIONS
 {
   .vectors :
@@ -27,6 +35,13 @@ SECTIONS
     . = ALIGN(4);
   } > FLASH0
 
+  /* hash of the layout of `.shared`; computed post-link by `lpc541xx-update-build-id` */
+  .build_id : ALIGN(4)
+  {
+    _build_id = .;
+    LONG(0);
+  } > FLASH0
+
   .bss : ALIGN(4)
   {
     *(.bss .bss.*);
@@ -48,8 +63,22 @@ SECTIONS
   _edata = ADDR(.data) + SIZEOF(.data);
   _sidata = LOADADDR(.data);
 
+  /* `#[ramfunc]` functions; copied into RAM by `start` */
+  .ramfunc : ALIGN(4)
+  {
+    *(.ramfunc .ramfunc.*);
+
+    . = ALIGN(4);
+  } > RAMFUNC AT > FLASH0
+
+  _sramfunc = ADDR(.ramfunc);
+  _eramfunc = ADDR(.ramfunc) + SIZEOF(.ramfunc);
+  _siramfunc = LOADADDR(.ramfunc);
+
   .shared : ALIGN(4)
   {
+    /* NOTE state of the `lpc541xx` crate; it must be at the same address on both cores */
+    KEEP(*(.shared.lpc541xx));
     KEEP(microamp-data.o(.shared));
     . = ALIGN(4);
   } > SRAM2 AT > FLASH0
@@ -58,6 +87,13 @@ SECTIONS
   _eshared = ADDR(.shared) + SIZEOF(.shared);
   _sishared = LOADADDR(.shared);
 
+  /* interned `blog!` format strings; not loaded into memory */
+  /* NOTE the section starts at address 1 so that no string is at address 0 */
+  .blog 1 (INFO) :
+  {
+    *(.blog .blog.*);
+  }
+
   /DISCARD/ :
   {
     *(.ARM.exidx.*);
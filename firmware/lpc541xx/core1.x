@@ -5,35 +5,46 @@ EXTERN(VECTORS);
 
 _stack_top = ORIGIN(SRAM1) + LENGTH(SRAM1);
 
+/* used by `lpc541xx-update-crc` to check the header */
+_eflash1 = ORIGIN(FLASH1) + LENGTH(FLASH1);
+
 SECTIONS
 {
+  /* NOTE checked by core #0 before it boots this image; see `src/core1.rs` */
   .pointers :
   {
+    LONG(0x4c504331); /* magic: "LPC1" */
+    LONG(3);          /* version of this block */
     LONG(ADDR(.vectors));
     LONG(ADDR(.data) + SIZEOF(.data));
+    LONG(0xffffffff); /* CRC-32 of the image; computed post-link by `lpc541xx-update-crc` */
+    LONG(0);          /* hash of the `.shared` layout; see `lpc541xx-update-build-id` */
   } > FLASH1
 
   .vectors :
   {
+    _svectors = .;
     LONG(_stack_top);
     LONG(start);
 
     KEEP(*(.vectors));
-  } > SRAM1 AT > FLASH1
+  } > IMAGE1 AT > FLASH1
 
   .text : ALIGN(4)
   {
     *(.text .text.*);
+    /* NOTE the whole image already runs from RAM */
+    *(.ramfunc .ramfunc.*);
 
     . = ALIGN(4);
-  } > SRAM1 AT > FLASH1
+  } > IMAGE1 AT > FLASH1
 
   .rodata : ALIGN(4)
   {
     *(.rodata .rodata.*);
 
     . = ALIGN(4);
-  } > SRAM1 AT > FLASH1
+  } > IMAGE1 AT > FLASH1
 
   .data : ALIGN(4)
   {
@@ -43,7 +54,7 @@ SECTIONS
     *(.data .data.*);
 
     . = ALIGN(4);
-  } > SRAM1 AT > FLASH1
+  } > IMAGE1 AT > FLASH1
 
   .bss : ALIGN(4)
   {
@@ -57,10 +68,22 @@ SECTIONS
 
   .shared (NOLOAD) : ALIGN(4)
   {
+    /* NOTE state of the `lpc541xx` crate; it must be at the same address on both cores */
+    KEEP(*(.shared.lpc541xx));
     KEEP(microamp-data.o(.shared));
     . = ALIGN(4);
   } > SRAM2
 
+  _sshared = ADDR(.shared);
+  _eshared = ADDR(.shared) + SIZEOF(.shared);
+
+  /* interned `blog!` format strings; not loaded into memory */
+  /* NOTE the section starts at address 1 so that no string is at address 0 */
+  .blog 1 (INFO) :
+  {
+    *(.blog .blog.*);
+  }
+
   /DISCARD/ :
   {
     *(.ARM.exidx.*);
@@ -117,6 +140,9 @@ PROVIDE(MAILBOX = DefaultHandler);
 
 ASSERT(ADDR(.pointers) == ORIGIN(FLASH1), ".pointers is not positioned where expected");
 
+/* VTOR ignores the low bits of the address of the vector table */
+ASSERT(_svectors % 256 == 0, "the vector table is not 256-byte aligned; check LPC541XX_SRAM0");
+
 /* check that all sections that need to be initialized are contiguous */
 ASSERT(ADDR(.vectors) + SIZEOF(.vectors) == ADDR(.text),
 ".vectors and .text are not contiguous");
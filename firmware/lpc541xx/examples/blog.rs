@@ -0,0 +1,61 @@
+//! Binary logging from both cores
+//!
+//! Core #0 frames go to ITM stimulus port 2; core #1 frames go to port 3 through core #0
+
+#![no_main]
+#![no_std]
+
+use core::sync::atomic::{self, Ordering};
+
+#[cfg(core = "1")]
+use lpc541xx::Interrupt_0;
+use lpc541xx::{blog, entry, log::Log};
+use microamp::shared;
+use panic_halt as _;
+
+#[shared]
+static LOG: Log<256> = Log::new();
+
+#[entry(core = 0)]
+fn main() -> ! {
+    const NVIC_ISER: *mut u32 = 0xE000_E100 as *mut u32;
+
+    let mut itm = cortex_m::Peripherals::take().unwrap().ITM;
+
+    blog!(itm.stim[2], "init");
+
+    // unmask GINT0
+    unsafe { NVIC_ISER.write_volatile(1 << 2) }
+
+    loop {
+        atomic::compiler_fence(Ordering::SeqCst);
+    }
+}
+
+#[entry(core = 1)]
+fn main() -> ! {
+    // every frame pends GINT0 on core #0
+    // NOTE(unsafe) this is the only `Writer`
+    let mut log = unsafe { LOG.writer() }.doorbell(Interrupt_0::GINT0);
+
+    for i in 0..10u32 {
+        blog!(log, "{} * {} = {}", i, i, i * i);
+    }
+
+    loop {
+        atomic::compiler_fence(Ordering::SeqCst);
+    }
+}
+
+#[cfg(core = "0")]
+#[no_mangle]
+#[allow(non_snake_case)]
+unsafe fn GINT0() {
+    use cortex_m::{itm, peripheral::ITM};
+
+    let mut itm = core::mem::transmute::<_, ITM>(());
+
+    // forward the frames as they are; they are decoded on the host
+    LOG.reader()
+        .drain(|frames| itm::write_all(&mut itm.stim[3], frames));
+}
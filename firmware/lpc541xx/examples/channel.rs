@@ -0,0 +1,57 @@
+//! Streaming data from core #1 to core #0 through a `Channel`
+
+#![no_main]
+#![no_std]
+
+use core::sync::atomic::{self, Ordering};
+
+#[cfg(core = "1")]
+use lpc541xx::Interrupt_0;
+use lpc541xx::{channel::Channel, entry};
+use microamp::shared;
+use panic_halt as _;
+
+#[shared]
+static CHANNEL: Channel<u32, 4> = Channel::new();
+
+#[entry(core = 0)]
+fn main() -> ! {
+    const NVIC_ISER: *mut u32 = 0xE000_E100 as *mut u32;
+
+    // unmask GINT0
+    unsafe { NVIC_ISER.write_volatile(1 << 2) }
+
+    loop {
+        atomic::compiler_fence(Ordering::SeqCst);
+    }
+}
+
+#[entry(core = 1)]
+fn main() -> ! {
+    // every `send` pends GINT0 on core #0
+    // NOTE(unsafe) this is the only `Sender`
+    let mut tx = unsafe { CHANNEL.sender(Interrupt_0::GINT0) };
+
+    for i in 0..10 {
+        // busy wait while the channel is full
+        while tx.send(i).is_err() {}
+    }
+
+    loop {
+        atomic::compiler_fence(Ordering::SeqCst);
+    }
+}
+
+#[cfg(core = "0")]
+#[no_mangle]
+#[allow(non_snake_case)]
+unsafe fn GINT0() {
+    use cortex_m::{iprintln, peripheral::ITM};
+
+    let mut itm = core::mem::transmute::<_, ITM>(());
+    let mut rx = CHANNEL.receiver();
+
+    while let Some(x) = rx.recv() {
+        iprintln!(&mut itm.stim[0], "[0] recv({})", x);
+    }
+}
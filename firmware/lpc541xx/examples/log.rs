@@ -0,0 +1,54 @@
+//! Logging from core #1 through core #0's ITM
+
+#![no_main]
+#![no_std]
+
+use core::sync::atomic::{self, Ordering};
+
+use lpc541xx::{entry, log::Log};
+#[cfg(core = "1")]
+use lpc541xx::{xprintln, Interrupt_0};
+use microamp::shared;
+use panic_halt as _;
+
+#[shared]
+static LOG: Log<256> = Log::new();
+
+#[entry(core = 0)]
+fn main() -> ! {
+    const NVIC_ISER: *mut u32 = 0xE000_E100 as *mut u32;
+
+    // unmask GINT0
+    unsafe { NVIC_ISER.write_volatile(1 << 2) }
+
+    loop {
+        atomic::compiler_fence(Ordering::SeqCst);
+    }
+}
+
+#[entry(core = 1)]
+fn main() -> ! {
+    // every record pends GINT0 on core #0
+    // NOTE(unsafe) this is the only `Writer`
+    let mut log = unsafe { LOG.writer() }.doorbell(Interrupt_0::GINT0);
+
+    for i in 0..10 {
+        xprintln!(log, "Hello, {}!", i);
+    }
+
+    loop {
+        atomic::compiler_fence(Ordering::SeqCst);
+    }
+}
+
+#[cfg(core = "0")]
+#[no_mangle]
+#[allow(non_snake_case)]
+unsafe fn GINT0() {
+    use cortex_m::peripheral::ITM;
+
+    let mut itm = core::mem::transmute::<_, ITM>(());
+
+    // the records of core #1 go to stimulus port 1
+    LOG.reader().drain_itm(&mut itm.stim[1]);
+}
@@ -1,13 +1,13 @@
 #![no_main]
 #![no_std]
 
-use core::sync::atomic::{self, AtomicBool, Ordering};
+use core::sync::atomic::{self, Ordering};
 
-use lpc541xx as _;
+use lpc541xx::sync::{Barrier, Wait};
 use panic_halt as _;
 
 #[microamp::shared]
-static BARRIER: AtomicBool = AtomicBool::new(false);
+static BARRIER: Barrier = Barrier::new(Wait::Sleep);
 
 #[no_mangle]
 unsafe extern "C" fn main() -> ! {
@@ -16,17 +16,15 @@ unsafe extern "C" fn main() -> ! {
 
         // unmask GINT0
         NVIC_ISER.write_volatile(1 << 2);
-
-        // unblock core #1
-        BARRIER.store(true, Ordering::Release);
     }
 
+    // core #1 must not pend GINT0 before core #0 has unmasked it
+    BARRIER.wait();
+
     if cfg!(core = "1") {
         const MAILBOX: usize = 0x4008_B000;
         const MAILBOX_IRQ1SET: *mut u32 = (MAILBOX + 0x14) as *mut u32;
 
-        while !BARRIER.load(Ordering::Acquire) {}
-
         // trigger core #0 interrupt #2 (GINT0)
         MAILBOX_IRQ1SET.write_volatile(1 << 2);
     }
@@ -1,63 +1,60 @@
 #![no_main]
 #![no_std]
 
-use core::sync::atomic::{self, Ordering};
-
 use cortex_m::asm;
 #[cfg(core = "0")]
 use cortex_m::iprintln;
-use lpc541xx as _;
+use lpc541xx::{entry, mutex::HwMutex};
 use microamp::shared;
 use panic_halt as _;
 
-// non-atomic variable
+// non-atomic variable protected by the MAILBOX mutex (a memory mapped register)
 #[shared] // <- means: same memory location on all the cores
-static mut SHARED: u64 = 0;
-
-// used to synchronize access to `SHARED`; this is a memory mapped register
-const MAILBOX_MUTEX: *mut u32 = 0x4008_b0f8 as *mut u32;
+static SHARED: HwMutex<u64> = HwMutex::new(0);
 
-#[no_mangle]
-unsafe extern "C" fn main() -> ! {
+#[entry(core = 0)]
+fn main() -> ! {
     // only core #0 has a functional ITM
-    #[cfg(core = "0")]
     let mut itm = cortex_m::Peripherals::take().unwrap().ITM;
 
+    // log a message through the stimulus port #0
+    count(|shared| iprintln!(&mut itm.stim[0], "[0] SHARED = {}", shared));
+
+    iprintln!(&mut itm.stim[0], "[0] DONE");
+
+    loop {}
+}
+
+#[entry(core = 1)]
+fn main() -> ! {
+    count(|_| {});
+
+    loop {}
+}
+
+// increments `SHARED` until it reaches some arbitrary value; `log` is called after each increment
+fn count(mut log: impl FnMut(u64)) {
     let mut done = false;
     while !done {
-        while MAILBOX_MUTEX.read_volatile() == 0 {
-            // busy wait while the lock is held by the other core
-        }
-        atomic::fence(Ordering::Acquire);
+        // busy wait while the lock is held by the other core
+        let mut shared = SHARED.lock().unwrap();
 
         // we acquired the lock; now we have exclusive access to `SHARED`
-        {
-            let shared = &mut SHARED;
-
-            if *shared >= 10 {
-                // stop at some arbitrary point
-                done = true;
-            } else {
-                *shared += 1;
-
-                // log a message through the stimulus port #0
-                #[cfg(core = "0")]
-                iprintln!(&mut itm.stim[0], "[0] SHARED = {}", *shared);
-            }
+        if *shared >= 10 {
+            // stop at some arbitrary point
+            done = true;
+        } else {
+            *shared += 1;
+
+            log(*shared);
         }
 
         // release the lock & unblock the other core
-        atomic::fence(Ordering::Release);
-        MAILBOX_MUTEX.write_volatile(1);
+        drop(shared);
 
         // artificial delay to let the *other* core take the mutex
         for _ in 0..1_000 {
             asm::nop();
         }
     }
-
-    #[cfg(core = "0")]
-    iprintln!(&mut itm.stim[0], "[0] DONE");
-
-    loop {}
 }
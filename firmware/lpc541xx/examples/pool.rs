@@ -0,0 +1,77 @@
+//! Moving large buffers from core #0 to core #1 without copying them
+
+#![no_main]
+#![no_std]
+
+use core::sync::atomic::{self, Ordering};
+
+#[cfg(core = "0")]
+use lpc541xx::Interrupt_1;
+use lpc541xx::{
+    channel::Channel,
+    entry,
+    pool::{Box, Pool},
+};
+use microamp::shared;
+use panic_halt as _;
+
+type Buffer = [u8; 1024];
+
+#[shared]
+static POOL: Pool<Buffer, 2> = Pool::new();
+
+// NOTE only the `Box`es, a pointer each, are copied through the channel
+#[shared]
+static CHANNEL: Channel<Box<Buffer>, 2> = Channel::new();
+
+#[entry(core = 0)]
+fn main() -> ! {
+    // every `send` pends GINT0 on core #1
+    // NOTE(unsafe) this is the only `Sender`
+    let mut tx = unsafe { CHANNEL.sender(Interrupt_1::GINT0) };
+
+    for i in 0..10 {
+        // busy wait until core #1 frees a block
+        let mut buffer = loop {
+            if let Ok(b) = POOL.alloc([0; 1024]) {
+                break b;
+            }
+        };
+
+        buffer.iter_mut().for_each(|x| *x = i);
+
+        // busy wait while the channel is full
+        while let Err(b) = tx.send(buffer) {
+            buffer = b;
+        }
+    }
+
+    loop {
+        atomic::compiler_fence(Ordering::SeqCst);
+    }
+}
+
+#[entry(core = 1)]
+fn main() -> ! {
+    const NVIC_ISER: *mut u32 = 0xE000_E100 as *mut u32;
+
+    // unmask GINT0
+    unsafe { NVIC_ISER.write_volatile(1 << 2) }
+
+    loop {
+        atomic::compiler_fence(Ordering::SeqCst);
+    }
+}
+
+#[cfg(core = "1")]
+#[no_mangle]
+#[allow(non_snake_case)]
+unsafe fn GINT0() {
+    let mut rx = CHANNEL.receiver();
+
+    while let Some(buffer) = rx.recv() {
+        let _sum = buffer.iter().map(|x| u32::from(*x)).sum::<u32>();
+
+        // `buffer` is dropped here and its block returns to the pool
+    }
+}
@@ -0,0 +1,54 @@
+//! Running a function from RAM
+
+#![no_main]
+#![no_std]
+
+use core::sync::atomic::{self, Ordering};
+
+use lpc541xx::{entry, ramfunc};
+use panic_halt as _;
+
+// on core #0 this runs from SRAMX; core #1 already runs from RAM
+#[ramfunc]
+fn fib(n: u32) -> u32 {
+    let (mut a, mut b) = (0, 1);
+
+    for _ in 0..n {
+        let c = a + b;
+        a = b;
+        b = c;
+    }
+
+    a
+}
+
+// only core #0 has a working ITM/SWO
+#[entry(core = 0)]
+fn main() -> ! {
+    use cortex_m::iprintln;
+
+    let x = fib(10);
+
+    // NOTE the ITM is initialized by pyOCD (see `pyocd_user.py`)
+    if let Some(mut p) = cortex_m::Peripherals::take() {
+        iprintln!(
+            &mut p.ITM.stim[0],
+            "[0] fib(10) = {} (`fib` @ {:?})",
+            x,
+            fib as fn(u32) -> u32
+        );
+    }
+
+    loop {
+        atomic::compiler_fence(Ordering::SeqCst);
+    }
+}
+
+#[entry(core = 1)]
+fn main() -> ! {
+    let _ = fib(10);
+
+    loop {
+        atomic::compiler_fence(Ordering::SeqCst);
+    }
+}
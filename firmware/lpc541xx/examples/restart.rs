@@ -0,0 +1,52 @@
+//! Restarting core #1 at runtime
+
+#![no_main]
+#![no_std]
+
+use core::sync::atomic::{self, Ordering};
+
+use lpc541xx::entry;
+use panic_halt as _;
+
+#[entry(core = 0)]
+fn main() -> ! {
+    use cortex_m::iprintln;
+    use lpc541xx::{
+        core1::{self, Core1},
+        Duration,
+    };
+
+    const TIMEOUT: u32 = 12_000; // CPU clock cycles or about one millisecond
+
+    let mut itm = cortex_m::Peripherals::take().unwrap().ITM;
+    let mut core1 = Core1::take().unwrap();
+
+    for _ in 0..3 {
+        match core1::wait_ready(Duration::from_cycles(TIMEOUT)) {
+            Ok(()) => iprintln!(&mut itm.stim[0], "[0] core #1 booted"),
+            Err(e) => {
+                iprintln!(&mut itm.stim[0], "[0] core #1 didn't boot: {:?}", e);
+
+                // continue on a single core
+                core1.reset();
+                break;
+            }
+        }
+
+        // NOTE core #1 stays in reset if its image is invalid
+        if core1.restart().is_err() {
+            break;
+        }
+    }
+
+    loop {
+        atomic::compiler_fence(Ordering::SeqCst);
+    }
+}
+
+#[entry(core = 1)]
+fn main() -> ! {
+    loop {
+        atomic::compiler_fence(Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,60 @@
+//! Offloading work to core #1 using remote procedure calls
+
+#![no_main]
+#![no_std]
+
+use core::sync::atomic::{self, Ordering};
+
+use lpc541xx::{entry, rpc::Rpc};
+#[cfg(core = "0")]
+use lpc541xx::{Duration, Interrupt_1};
+use microamp::shared;
+use panic_halt as _;
+
+#[cfg(core = "0")]
+const TIMEOUT: u32 = 12_000; // CPU clock cycles or about one millisecond
+
+#[shared]
+static RPC: Rpc<u32, u64> = Rpc::new();
+
+// only core #0 has a functional ITM
+#[entry(core = 0)]
+fn main() -> ! {
+    use cortex_m::iprintln;
+
+    let mut itm = cortex_m::Peripherals::take().unwrap().ITM;
+
+    // every request pends GINT0 on core #1
+    // NOTE(unsafe) this is the only `Client`
+    let mut client = unsafe { RPC.client() }.doorbell(Interrupt_1::GINT0);
+
+    for x in 0..5 {
+        match client.call(x, Duration::from_cycles(TIMEOUT)) {
+            Ok(y) => iprintln!(&mut itm.stim[0], "[0] square({}) = {}", x, y),
+            Err(e) => iprintln!(&mut itm.stim[0], "[0] square({}): {:?}", x, e),
+        }
+    }
+
+    loop {
+        atomic::compiler_fence(Ordering::SeqCst);
+    }
+}
+
+#[entry(core = 1)]
+fn main() -> ! {
+    const NVIC_ISER: *mut u32 = 0xE000_E100 as *mut u32;
+
+    // unmask GINT0
+    unsafe { NVIC_ISER.write_volatile(1 << 2) }
+
+    loop {
+        atomic::compiler_fence(Ordering::SeqCst);
+    }
+}
+
+#[cfg(core = "1")]
+#[no_mangle]
+#[allow(non_snake_case)]
+unsafe fn GINT0() {
+    RPC.server().serve(|x| u64::from(x) * u64::from(x));
+}
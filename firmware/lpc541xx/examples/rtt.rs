@@ -0,0 +1,34 @@
+//! Logging from both cores over RTT; the bytes sent by the host are echoed back
+
+#![no_main]
+#![no_std]
+
+use core::fmt::Write;
+
+use lpc541xx::{entry, rtt};
+use panic_halt as _;
+
+#[entry(core = 0)]
+fn main() -> ! {
+    echo(0)
+}
+
+#[entry(core = 1)]
+fn main() -> ! {
+    echo(1)
+}
+
+fn echo(core: u8) -> ! {
+    let (mut up, mut down) = rtt::init().unwrap();
+
+    writeln!(up, "[{}] Hello, world!", core).ok();
+
+    let mut buf = [0; rtt::DOWN_SIZE];
+    loop {
+        let n = down.read(&mut buf);
+
+        if n != 0 {
+            up.write(&buf[..n]);
+        }
+    }
+}
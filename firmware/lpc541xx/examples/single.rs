@@ -0,0 +1,29 @@
+//! Single-core application
+//!
+//! Build with `cargo build --example single --features single-core --release`
+
+#![no_main]
+#![no_std]
+
+use core::sync::atomic::{self, Ordering};
+
+use cortex_m::iprintln;
+use lpc541xx::{entry, Core};
+use panic_halt as _;
+
+// NOTE `core = 0` is also the entry point of single-core applications
+#[entry(core = 0)]
+fn main() -> ! {
+    // NOTE the ITM is initialized by pyOCD (see `pyocd_user.py`)
+    if let Some(mut p) = cortex_m::Peripherals::take() {
+        iprintln!(
+            &mut p.ITM.stim[0],
+            "Hello from a single core! core #1 running: {}",
+            Core::Cm0Plus.is_running()
+        );
+    }
+
+    loop {
+        atomic::compiler_fence(Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,151 @@
+#![deny(rust_2018_compatibility)]
+#![deny(rust_2018_idioms)]
+#![deny(warnings)]
+
+//! Attributes of the `lpc541xx` crate; see the re-exports in that crate
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{
+    parse, parse_macro_input, spanned::Spanned, AttributeArgs, ItemFn, Lit, Meta, NestedMeta,
+    ReturnType, Type, Visibility,
+};
+
+/// Declares the entry point of a core
+///
+/// The function must have signature `fn() -> !`; it's called by `start` once the `static`
+/// variables of the core have been initialized. Each core needs exactly one entry point.
+///
+/// NOTE `core = 0` is also the entry point of single-core applications, which are not built with
+/// `cargo microamp`
+///
+/// ``` ignore
+/// #[entry(core = 0)]
+/// fn main() -> ! {
+///     // ..
+/// }
+///
+/// #[entry(core = 1)]
+/// fn main() -> ! {
+///     // ..
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn entry(args: TokenStream, input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as AttributeArgs);
+    let f = parse_macro_input!(input as ItemFn);
+
+    let core = match core(&args) {
+        Ok(core) => core,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    let valid_signature = f.sig.constness.is_none()
+        && f.sig.asyncness.is_none()
+        && f.sig.unsafety.is_none()
+        && f.sig.abi.is_none()
+        && matches!(f.vis, Visibility::Inherited)
+        && f.sig.inputs.is_empty()
+        && f.sig.generics.params.is_empty()
+        && f.sig.generics.where_clause.is_none()
+        && f.sig.variadic.is_none()
+        && match f.sig.output {
+            ReturnType::Default => false,
+            ReturnType::Type(_, ref ty) => matches!(**ty, Type::Never(_)),
+        };
+
+    if !valid_signature {
+        return parse::Error::new(
+            f.sig.span(),
+            "`#[entry]` function must have signature `fn() -> !`",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    // `cargo microamp` builds core #N with `--cfg core="N"`; single-core applications don't set
+    // the `core` cfg
+    let cfg = if core == 0 {
+        quote!(#[cfg(not(core = "1"))])
+    } else {
+        quote!(#[cfg(core = "1")])
+    };
+    let ident = &f.sig.ident;
+    let trampoline = format_ident!("__lpc541xx_entry_{}", core);
+
+    quote!(
+        #cfg
+        #[doc(hidden)]
+        #[export_name = "main"]
+        pub unsafe fn #trampoline() -> ! {
+            #ident()
+        }
+
+        #cfg
+        #f
+    )
+    .into()
+}
+
+// parses the `core = N` argument of `#[entry]`
+fn core(args: &[NestedMeta]) -> parse::Result<u8> {
+    const MSG: &str = "expected `core = 0` or `core = 1`";
+
+    match args {
+        [NestedMeta::Meta(Meta::NameValue(nv))] if nv.path.is_ident("core") => match &nv.lit {
+            Lit::Int(lit) => match lit.base10_parse::<u8>() {
+                Ok(core) if core < 2 => Ok(core),
+                _ => Err(parse::Error::new(lit.span(), MSG)),
+            },
+            lit => Err(parse::Error::new(lit.span(), MSG)),
+        },
+        _ => Err(parse::Error::new(Span::call_site(), MSG)),
+    }
+}
+
+/// Runs a function from RAM
+///
+/// The function is placed in the `.ramfunc` section, which `start` copies from Flash into SRAMX, or
+/// into SRAM0 if core #1 runs from SRAMX. Use it for code, e.g. interrupt handlers, that must not
+/// contend with the other core for the Flash or pay its wait states.
+///
+/// NOTE the function is never inlined. On core #1, which already runs from RAM, this attribute has
+/// no other effect.
+///
+/// ``` ignore
+/// #[ramfunc]
+/// fn filter(sample: i16) -> i16 {
+///     // ..
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn ramfunc(args: TokenStream, input: TokenStream) -> TokenStream {
+    if !args.is_empty() {
+        return parse::Error::new(Span::call_site(), "this attribute accepts no arguments")
+            .to_compile_error()
+            .into();
+    }
+
+    let f = parse_macro_input!(input as ItemFn);
+
+    if f.sig.constness.is_some() || f.sig.asyncness.is_some() {
+        return parse::Error::new(
+            f.sig.span(),
+            "`#[ramfunc]` can't be used on `const` or `async` functions",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    // one section per function so that unused functions can be discarded by the linker
+    let section = format!(".ramfunc.{}", f.sig.ident);
+
+    // NOTE `inline(never)` because inlining the function into its caller would run it from Flash
+    quote!(
+        #[link_section = #section]
+        #[inline(never)]
+        #f
+    )
+    .into()
+}
@@ -46,7 +46,8 @@ def did_reset(core):
         # TraceBusID | SWOEN | ITMEN
         core.write_memory(ITM_TCR, (1 << 16) | (1 << 3) | (1 << 0))
 
-        # enable stimulus port 0
-        core.write_memory(ITM_TER0, 1)
+        # enable stimulus ports 0 and 1 (log records of the Cortex-M0+) and 2 and 3 (`blog!` frames
+        # of each core)
+        core.write_memory(ITM_TER0, 0b1111)
 
         # print('Cortex-M4F: ITM enabled')
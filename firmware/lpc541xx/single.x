@@ -0,0 +1,148 @@
+/* Linker script for single-core applications: only the Cortex-M4F runs */
+/* NOTE core #1 is never booted so its Flash and RAM are given to core #0 */
+/* NOTE `memory.x` is generated by `build.rs` for the selected part */
+INCLUDE memory.x;
+
+ENTRY(_start);
+EXTERN(VECTORS);
+
+/* NOTE the stack is at the start of SRAM0, below the `static` variables, because SRAM1 and SRAM2
+   have no clock until `start` enables them and `start` uses the stack before that */
+_stack_size = 16K;
+
+SECTIONS
+{
+  .vectors :
+  {
+    LONG(_stack_top);
+    LONG(_start);
+
+    KEEP(*(.vectors));
+  } > FLASH
+
+  .text : ALIGN(4)
+  {
+    *(.text .text.*);
+  } > FLASH
+
+  .rodata : ALIGN(4)
+  {
+    *(.rodata .rodata.*);
+
+    . = ALIGN(4);
+  } > FLASH
+
+  .stack (NOLOAD) :
+  {
+    . += _stack_size;
+  } > RAM
+
+  _stack_top = ADDR(.stack) + SIZEOF(.stack);
+
+  .bss : ALIGN(4)
+  {
+    *(.bss .bss.*);
+
+    . = ALIGN(4);
+  } > RAM
+
+  _sbss = ADDR(.bss);
+  _ebss = ADDR(.bss) + SIZEOF(.bss);
+
+  .data : ALIGN(4)
+  {
+    *(.data .data.*);
+
+    . = ALIGN(4);
+  } > RAM AT > FLASH
+
+  _sdata = ADDR(.data);
+  _edata = ADDR(.data) + SIZEOF(.data);
+  _sidata = LOADADDR(.data);
+
+  /* `#[ramfunc]` functions; copied into RAM by `start` */
+  .ramfunc : ALIGN(4)
+  {
+    *(.ramfunc .ramfunc.*);
+
+    . = ALIGN(4);
+  } > SRAMX AT > FLASH
+
+  _sramfunc = ADDR(.ramfunc);
+  _eramfunc = ADDR(.ramfunc) + SIZEOF(.ramfunc);
+  _siramfunc = LOADADDR(.ramfunc);
+
+  /* NOTE there are no `#[shared]` variables; only the state of the `lpc541xx` crate */
+  .shared : ALIGN(4)
+  {
+    KEEP(*(.shared.lpc541xx));
+    . = ALIGN(4);
+  } > RAM AT > FLASH
+
+  _sshared = ADDR(.shared);
+  _eshared = ADDR(.shared) + SIZEOF(.shared);
+  _sishared = LOADADDR(.shared);
+
+  /* interned `blog!` format strings; not loaded into memory */
+  /* NOTE the section starts at address 1 so that no string is at address 0 */
+  .blog 1 (INFO) :
+  {
+    *(.blog .blog.*);
+  }
+
+  /DISCARD/ :
+  {
+    *(.ARM.exidx.*);
+    *(.ARM.extab.*);
+  }
+}
+
+PROVIDE(NMI = DefaultHandler);
+PROVIDE(HardFault = DefaultHandler);
+PROVIDE(MemoryManagement = DefaultHandler);
+PROVIDE(BusFault = DefaultHandler);
+PROVIDE(UsageFault = DefaultHandler);
+PROVIDE(SVCall = DefaultHandler);
+PROVIDE(DebugMonitor = DefaultHandler);
+PROVIDE(PendSV = DefaultHandler);
+PROVIDE(SysTick = DefaultHandler);
+PROVIDE(WDT = DefaultHandler);
+PROVIDE(DMA = DefaultHandler);
+PROVIDE(GINT0 = DefaultHandler);
+PROVIDE(GINT1 = DefaultHandler);
+PROVIDE(PIN_INT0 = DefaultHandler);
+PROVIDE(PIN_INT1 = DefaultHandler);
+PROVIDE(PIN_INT2 = DefaultHandler);
+PROVIDE(PIN_INT3 = DefaultHandler);
+PROVIDE(UTICK = DefaultHandler);
+PROVIDE(MRT = DefaultHandler);
+PROVIDE(CTIMER0 = DefaultHandler);
+PROVIDE(CTIMER1 = DefaultHandler);
+PROVIDE(SCT0 = DefaultHandler);
+PROVIDE(CTIMER3 = DefaultHandler);
+PROVIDE(Flexcomm0 = DefaultHandler);
+PROVIDE(Flexcomm1 = DefaultHandler);
+PROVIDE(Flexcomm2 = DefaultHandler);
+PROVIDE(Flexcomm3 = DefaultHandler);
+PROVIDE(Flexcomm4 = DefaultHandler);
+PROVIDE(Flexcomm5 = DefaultHandler);
+PROVIDE(Flexcomm6 = DefaultHandler);
+PROVIDE(Flexcomm7 = DefaultHandler);
+PROVIDE(ADC0_SEQA = DefaultHandler);
+PROVIDE(ADC0_SEQB = DefaultHandler);
+PROVIDE(ADC0_THCMP = DefaultHandler);
+PROVIDE(DMIC = DefaultHandler);
+PROVIDE(HWVAD = DefaultHandler);
+PROVIDE(USB_WAKEUP = DefaultHandler);
+PROVIDE(USB = DefaultHandler);
+PROVIDE(RTC = DefaultHandler);
+PROVIDE(MAILBOX = DefaultHandler);
+PROVIDE(PIN_INT4 = DefaultHandler);
+PROVIDE(PIN_INT5 = DefaultHandler);
+PROVIDE(PIN_INT6 = DefaultHandler);
+PROVIDE(PIN_INT7 = DefaultHandler);
+PROVIDE(CTIMER2 = DefaultHandler);
+PROVIDE(CTIMER4 = DefaultHandler);
+
+ASSERT(ADDR(.stack) == ORIGIN(RAM), ".stack is not positioned where expected");
+ASSERT(_stack_top <= ORIGIN(RAM) + 64K, "the stack doesn't fit in the SRAM0 bank");
@@ -0,0 +1,213 @@
+//! Binary logging with deferred formatting
+//!
+//! `blog!` doesn't format its arguments on the device. Instead it sends a *frame* that contains the
+//! index of the interned format string and the raw arguments; the host turns the frames back into
+//! text using the `lpc541xx-blog` tool, which reads the format strings from the ELF of each core.
+//!
+//! Format strings are stored in the `.blog` section, which is not loaded into Flash. Only `{}`
+//! placeholders are supported; their contents, e.g. `{:?}`, are passed as-is to the decoder.
+//!
+//! Frames can be sent to an ITM stimulus port (core #0) or to a `log::Writer` (core #1), which
+//! core #0 then forwards, without decoding, to an ITM stimulus port using `log::Reader::drain`.
+//!
+//! # Frame format
+//!
+//! - length of the rest of the frame, 1 byte
+//! - address of the format string in the `.blog` section, LEB128
+//! - for each argument, a tag byte followed by the value:
+//!   - `0` unsigned integer, LEB128
+//!   - `1` signed integer, zigzag + LEB128
+//!   - `2` `bool`, 1 byte
+//!   - `3` `char`, LEB128
+//!   - `4` `f32`, 4 bytes little endian
+//!   - `5` `str`, length as LEB128 followed by the UTF-8 bytes
+
+use crate::log;
+
+/// Sends a binary log frame to a `blog::Sink`
+///
+/// Returns `false` if the frame was dropped, because the arguments don't fit in a frame or because
+/// the sink is full
+///
+/// ``` ignore
+/// blog!(itm.stim[2], "x = {}, y = {}", x, y);
+/// ```
+#[macro_export]
+macro_rules! blog {
+    ($sink:expr, $fmt:literal $(, $arg:expr)* $(,)?) => {{
+        const FMT: &str = concat!($fmt, "\0");
+
+        #[link_section = ".blog"]
+        static INTERNED: [u8; FMT.len()] = $crate::blog::intern(FMT);
+
+        let mut frame = $crate::blog::Frame::new(&INTERNED as *const _ as usize);
+        $($crate::blog::Arg::encode(&$arg, &mut frame);)*
+        frame.send(&mut $sink)
+    }};
+}
+
+#[doc(hidden)]
+pub const fn intern<const N: usize>(s: &str) -> [u8; N] {
+    let mut bytes = [0; N];
+
+    let mut i = 0;
+    while i < N {
+        bytes[i] = s.as_bytes()[i];
+        i += 1;
+    }
+
+    bytes
+}
+
+/// A destination for frames
+pub trait Sink {
+    /// Sends a complete frame; returns `false` if the frame was dropped
+    fn frame(&mut self, frame: &[u8]) -> bool;
+}
+
+#[cfg(master)]
+impl Sink for cortex_m::peripheral::itm::Stim {
+    fn frame(&mut self, frame: &[u8]) -> bool {
+        cortex_m::itm::write_all(self, frame);
+
+        true
+    }
+}
+
+impl<const N: usize> Sink for log::Writer<N> {
+    fn frame(&mut self, frame: &[u8]) -> bool {
+        log::Writer::frame(self, frame)
+    }
+}
+
+/// A frame being encoded
+#[doc(hidden)]
+pub struct Frame {
+    buffer: [u8; Frame::CAPACITY],
+    len: usize,
+    overflow: bool,
+}
+
+impl Frame {
+    // NOTE the length byte limits a frame to 255 bytes; smaller frames use less stack
+    const CAPACITY: usize = 64;
+
+    pub fn new(index: usize) -> Self {
+        let mut frame = Frame {
+            buffer: [0; Frame::CAPACITY],
+            // reserve the length byte
+            len: 1,
+            overflow: false,
+        };
+
+        frame.leb128(index as u32);
+
+        frame
+    }
+
+    pub fn send(mut self, sink: &mut impl Sink) -> bool {
+        if self.overflow {
+            return false;
+        }
+
+        self.buffer[0] = (self.len - 1) as u8;
+        sink.frame(&self.buffer[..self.len])
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        if let Some(buffer) = self.buffer.get_mut(self.len..self.len + bytes.len()) {
+            buffer.copy_from_slice(bytes);
+            self.len += bytes.len();
+        } else {
+            self.overflow = true;
+        }
+    }
+
+    fn leb128(&mut self, mut x: u32) {
+        loop {
+            let byte = (x & 0x7f) as u8;
+            x >>= 7;
+
+            if x == 0 {
+                break self.bytes(&[byte]);
+            }
+
+            self.bytes(&[byte | 0x80]);
+        }
+    }
+}
+
+/// Types that can be `blog!` arguments
+pub trait Arg {
+    #[doc(hidden)]
+    fn encode(&self, frame: &mut Frame);
+}
+
+macro_rules! unsigned {
+    ($($ty:ty),*) => {
+        $(
+            impl Arg for $ty {
+                fn encode(&self, frame: &mut Frame) {
+                    frame.bytes(&[0]);
+                    frame.leb128(*self as u32);
+                }
+            }
+        )*
+    };
+}
+
+unsigned!(u8, u16, u32, usize);
+
+macro_rules! signed {
+    ($($ty:ty),*) => {
+        $(
+            impl Arg for $ty {
+                fn encode(&self, frame: &mut Frame) {
+                    let x = *self as i32;
+
+                    frame.bytes(&[1]);
+                    frame.leb128(((x << 1) ^ (x >> 31)) as u32);
+                }
+            }
+        )*
+    };
+}
+
+signed!(i8, i16, i32, isize);
+
+impl Arg for bool {
+    fn encode(&self, frame: &mut Frame) {
+        frame.bytes(&[2, *self as u8]);
+    }
+}
+
+impl Arg for char {
+    fn encode(&self, frame: &mut Frame) {
+        frame.bytes(&[3]);
+        frame.leb128(*self as u32);
+    }
+}
+
+impl Arg for f32 {
+    fn encode(&self, frame: &mut Frame) {
+        frame.bytes(&[4]);
+        frame.bytes(&self.to_bits().to_le_bytes());
+    }
+}
+
+impl Arg for str {
+    fn encode(&self, frame: &mut Frame) {
+        frame.bytes(&[5]);
+        frame.leb128(self.len() as u32);
+        frame.bytes(self.as_bytes());
+    }
+}
+
+impl<T> Arg for &'_ T
+where
+    T: Arg + ?Sized,
+{
+    fn encode(&self, frame: &mut Frame) {
+        T::encode(self, frame)
+    }
+}
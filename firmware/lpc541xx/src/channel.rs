@@ -0,0 +1,153 @@
+//! Single-producer single-consumer channels between the two cores
+
+use core::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{CoreInterrupt, Doorbell, PowerOfTwo};
+
+/// A fixed capacity channel used to send values from one core to the other
+///
+/// The channel must be shared between the cores and only uses atomic loads and stores; see the
+/// [`mutex`](crate::mutex) module docs. Values are moved between cores bit by bit so they must be
+/// meaningful to both cores, e.g. they must not contain references to one core's `static`s.
+///
+/// NOTE `N` must be a power of two
+pub struct Channel<T, const N: usize> {
+    buffer: UnsafeCell<MaybeUninit<[T; N]>>,
+    // NOTE only written by the `Receiver`
+    head: AtomicUsize,
+    // NOTE only written by the `Sender`
+    tail: AtomicUsize,
+}
+
+unsafe impl<T, const N: usize> Sync for Channel<T, N> where T: Send {}
+
+impl<T, const N: usize> Channel<T, N> {
+    /// Creates an empty channel
+    pub const fn new() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = PowerOfTwo::<N>::ASSERT;
+
+        Self {
+            buffer: UnsafeCell::new(MaybeUninit::uninit()),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the sending end of this channel
+    ///
+    /// Every successful `send` pends the interrupt `int` on the core that owns the `Receiver`
+    ///
+    /// # Safety
+    ///
+    /// There must be at most one `Sender` for this channel at any point in time
+    pub unsafe fn sender<I>(&'static self, int: I) -> Sender<T, N>
+    where
+        I: CoreInterrupt,
+    {
+        Sender {
+            channel: self,
+            doorbell: Doorbell::new(int),
+        }
+    }
+
+    /// Returns the receiving end of this channel
+    ///
+    /// # Safety
+    ///
+    /// There must be at most one `Receiver` for this channel at any point in time
+    pub unsafe fn receiver(&'static self) -> Receiver<T, N> {
+        Receiver { channel: self }
+    }
+
+    fn slot(&self, index: usize) -> *mut T {
+        unsafe { (self.buffer.get() as *mut T).add(index % N) }
+    }
+}
+
+/// The sending end of a `Channel`
+pub struct Sender<T, const N: usize>
+where
+    T: 'static,
+{
+    channel: &'static Channel<T, N>,
+    doorbell: Doorbell,
+}
+
+unsafe impl<T, const N: usize> Send for Sender<T, N> where T: Send {}
+
+impl<T, const N: usize> Sender<T, N> {
+    /// Sends a `value` to the other end of the channel and rings its doorbell
+    ///
+    /// Returns back the `value` if the channel is full
+    pub fn send(&mut self, value: T) -> Result<(), T> {
+        // NOTE(Relaxed) we are the only writer of `tail`
+        let tail = self.channel.tail.load(Ordering::Relaxed);
+        let head = self.channel.head.load(Ordering::Acquire);
+
+        if tail.wrapping_sub(head) == N {
+            return Err(value);
+        }
+
+        unsafe { self.channel.slot(tail).write(value) }
+        self.channel
+            .tail
+            .store(tail.wrapping_add(1), Ordering::Release);
+
+        self.doorbell.ring();
+
+        Ok(())
+    }
+
+    /// Returns `true` if the channel is full
+    pub fn is_full(&self) -> bool {
+        let head = self.channel.head.load(Ordering::Acquire);
+        let tail = self.channel.tail.load(Ordering::Relaxed);
+
+        tail.wrapping_sub(head) == N
+    }
+}
+
+/// The receiving end of a `Channel`
+pub struct Receiver<T, const N: usize>
+where
+    T: 'static,
+{
+    channel: &'static Channel<T, N>,
+}
+
+unsafe impl<T, const N: usize> Send for Receiver<T, N> where T: Send {}
+
+impl<T, const N: usize> Receiver<T, N> {
+    /// Receives a value from the other end of the channel
+    ///
+    /// Returns `None` if the channel is empty
+    pub fn recv(&mut self) -> Option<T> {
+        // NOTE(Relaxed) we are the only writer of `head`
+        let head = self.channel.head.load(Ordering::Relaxed);
+        let tail = self.channel.tail.load(Ordering::Acquire);
+
+        if head == tail {
+            return None;
+        }
+
+        let value = unsafe { self.channel.slot(head).read() };
+        self.channel
+            .head
+            .store(head.wrapping_add(1), Ordering::Release);
+
+        Some(value)
+    }
+
+    /// Returns `true` if the channel is empty
+    pub fn is_empty(&self) -> bool {
+        let head = self.channel.head.load(Ordering::Relaxed);
+        let tail = self.channel.tail.load(Ordering::Acquire);
+
+        head == tail
+    }
+}
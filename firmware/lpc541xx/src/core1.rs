@@ -0,0 +1,392 @@
+//! Control over core #1, the Cortex-M0+
+//!
+//! Only available on core #0
+//!
+//! NOTE after reset the Cortex-M0+ runs the common reset handler, which jumps to the address in
+//! `SYSCON_CPBOOT` using the stack pointer in `SYSCON_CPSTACK`
+
+use core::sync::atomic::{self, AtomicBool, Ordering};
+
+use crate::{mailbox_clear, mutex, sync::Wait, Core, Duration, Instant, SHARED};
+
+const SYSCON_CPUCTRL: *mut u32 = 0x4000_0800 as *mut u32;
+const SYSCON_CPBOOT: *mut u32 = 0x4000_0804 as *mut u32;
+const SYSCON_CPSTACK: *mut u32 = 0x4000_0808 as *mut u32;
+
+// writes to CPUCTRL must include this key
+const KEY: u32 = 0xc0c4 << 16;
+// the M4 is the master (0) and its clock is enabled (2); bits 6 and 15 must be written as ones
+const CPUCTRL: u32 = KEY | (1 << 15) | (1 << 6) | (1 << 2) | (1 << 0);
+const CM0CLKEN: u32 = 1 << 3;
+const CM0RSTEN: u32 = 1 << 5;
+
+// header of the image of core #1; see the `.pointers` section in `core1.x`
+#[repr(C)]
+struct Pointers {
+    magic: u32,
+    version: u32,
+    // range of SRAM1 that the image is copied into
+    start: usize,
+    end: usize,
+    // CRC-32 (the one used by zlib) of the `end - start` bytes that follow this header
+    crc: u32,
+    // hash of the layout of the `.shared` section
+    build_id: u32,
+}
+
+const MAGIC: u32 = 0x4c50_4331; // "LPC1"
+const VERSION: u32 = 3;
+
+static TAKEN: AtomicBool = AtomicBool::new(false);
+
+// why core #1 was not booted
+// NOTE only written during `start` and `Core1::restart`
+static mut IMAGE_ERROR: Option<ImageError> = None;
+
+/// Problems found in the image of core #1
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ImageError {
+    /// FLASH1 is erased
+    Erased,
+
+    /// The image header doesn't start with the expected magic word
+    BadMagic,
+
+    /// The image header has an unsupported version
+    BadVersion(u32),
+
+    /// The image doesn't fit in FLASH1 or SRAM1, or its stack pointer or entry point are not in
+    /// SRAM1
+    OutOfRange,
+
+    /// The CRC of the image doesn't match the CRC in its header
+    ///
+    /// NOTE `lpc541xx-update-crc` must be run on the ELF of core #1 after linking
+    BadCrc,
+
+    /// The image was built with a different layout of `#[shared]` variables than this image
+    ///
+    /// NOTE `lpc541xx-update-build-id` must be run on the ELFs of both cores after linking
+    BuildIdMismatch,
+
+    /// The build ID of this image, or of the image of core #1, was never written
+    ///
+    /// NOTE `lpc541xx-update-build-id` must be run on the ELFs of both cores after linking
+    Unstamped,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// Core #1 is halted or held in reset
+    Halted,
+
+    /// Core #1 did not finish booting in time
+    TimedOut,
+
+    /// Core #1 was not booted because its image is invalid
+    InvalidImage(ImageError),
+}
+
+/// Waits at most `timeout` for core #1 to finish booting, that is to reach its `main` function
+///
+/// This core sleeps while waiting. On error, core #1 can be held in reset with `Core1::reset` to
+/// continue in single-core mode: sending messages to a core held in reset fails with a
+/// `CoreHalted` error instead of silently getting lost.
+pub fn wait_ready(timeout: Duration) -> Result<(), Error> {
+    if let Some(e) = image_error() {
+        return Err(Error::InvalidImage(e));
+    }
+
+    let deadline = Instant::deadline(timeout);
+
+    Wait::Sleep
+        .until_deadline(deadline, || {
+            if SHARED.core1_ready.load(Ordering::Acquire) {
+                Some(Ok(()))
+            } else if !Core::Cm0Plus.is_running() {
+                Some(Err(Error::Halted))
+            } else {
+                None
+            }
+        })
+        .unwrap_or(Err(Error::TimedOut))
+}
+
+/// Returns `true` if core #1 has finished booting
+pub fn is_ready() -> bool {
+    SHARED.core1_ready.load(Ordering::Acquire)
+}
+
+/// Returns the reason why core #1 was not booted, if its image was found to be invalid
+pub fn image_error() -> Option<ImageError> {
+    unsafe { IMAGE_ERROR }
+}
+
+/// Handle to core #1
+pub struct Core1 {
+    _0: (),
+}
+
+impl Core1 {
+    /// Returns the handle to core #1
+    ///
+    /// Returns `None` if the handle has already been taken
+    pub fn take() -> Option<Self> {
+        let taken = cortex_m::interrupt::free(|_| {
+            let taken = TAKEN.load(Ordering::Relaxed);
+            TAKEN.store(true, Ordering::Relaxed);
+            taken
+        });
+
+        if taken {
+            None
+        } else {
+            Some(Core1 { _0: () })
+        }
+    }
+
+    /// Returns `true` if core #1 is clocked and out of reset
+    pub fn is_running(&self) -> bool {
+        Core::Cm0Plus.is_running()
+    }
+
+    /// Returns `true` if core #1 is held in reset
+    pub fn is_in_reset(&self) -> bool {
+        unsafe { SYSCON_CPUCTRL.read_volatile() & CM0RSTEN != 0 }
+    }
+
+    /// Stops the clock of core #1
+    ///
+    /// Core #1 stays frozen, with its state intact, until `resume` is called
+    pub fn halt(&mut self) {
+        modify(|cpuctrl| cpuctrl & !CM0CLKEN)
+    }
+
+    /// Restarts the clock of core #1 after a `halt`
+    pub fn resume(&mut self) {
+        modify(|cpuctrl| cpuctrl | CM0CLKEN)
+    }
+
+    /// Holds core #1 in reset
+    pub fn reset(&mut self) {
+        // NOTE the clock must be running for the reset to take effect
+        modify(|cpuctrl| cpuctrl | CM0CLKEN | CM0RSTEN);
+
+        SHARED.core1_ready.store(false, Ordering::Release);
+    }
+
+    /// Releases core #1 from reset
+    ///
+    /// Core #1 starts executing at the address set with `set_boot`
+    pub fn release(&mut self) {
+        modify(|cpuctrl| (cpuctrl | CM0CLKEN) & !CM0RSTEN)
+    }
+
+    /// Sets the initial stack pointer and entry point of core #1
+    ///
+    /// This takes effect the next time core #1 is released from reset
+    ///
+    /// # Safety
+    ///
+    /// `entry` must be the address of a Thumb function that never returns and `stack` must point
+    /// to the end of memory that's reserved for core #1's stack
+    pub unsafe fn set_boot(&mut self, stack: u32, entry: u32) {
+        SYSCON_CPSTACK.write_volatile(stack);
+        SYSCON_CPBOOT.write_volatile(entry);
+    }
+
+    /// Restarts the image of core #1 from scratch
+    ///
+    /// Core #1 is held in reset, its `static` variables are re-initialized and it's released from
+    /// reset at the entry point of its image. Use this to recover from a crashed or hung core #1;
+    /// use `wait_ready` to wait until it has booted again.
+    ///
+    /// The state of this crate that's shared with core #1 is reset as well: its mailbox is
+    /// cleared, its mailbox payload mode is disabled and the hardware mutex is released if core #1
+    /// was holding it. `#[shared]` variables are *not* re-initialized.
+    ///
+    /// Core #1 stays in reset if its image is invalid
+    pub fn restart(&mut self) -> Result<(), ImageError> {
+        self.reset();
+
+        // release the hardware mutex if core #1 died holding it
+        mutex::release_other();
+
+        SHARED.payload_mask[Core::Cm0Plus as usize].store(0, Ordering::Release);
+        mailbox_clear(Core::Cm0Plus, !0);
+
+        unsafe {
+            let (stack, entry) = init()?;
+            self.set_boot(stack, entry);
+        }
+
+        self.release();
+
+        Ok(())
+    }
+}
+
+fn modify(f: impl FnOnce(u32) -> u32) {
+    unsafe {
+        let cpuctrl = SYSCON_CPUCTRL.read_volatile() & (CM0CLKEN | CM0RSTEN);
+        SYSCON_CPUCTRL.write_volatile(CPUCTRL | f(cpuctrl));
+    }
+}
+
+// Validates the image of core #1, initializes its `static` variables and returns its initial stack
+// pointer and entry point
+//
+// The outcome is recorded in `IMAGE_ERROR`
+unsafe fn init() -> Result<(u32, u32), ImageError> {
+    let res = validate();
+    IMAGE_ERROR = res.err();
+    let pointers = res?;
+    let header = pointers.read_volatile();
+
+    let _sslave = header.start as *mut u32;
+    let _eslave = header.end as *mut u32;
+    let _sislave = pointers.add(1) as *const u32;
+    r0::init_data(_sslave, _eslave, _sislave);
+
+    // the start of the image is also its vector table
+    Ok((_sslave.read(), _sslave.add(1).read()))
+}
+
+unsafe fn validate() -> Result<*const Pointers, ImageError> {
+    extern "C" {
+        static _sflash1: u32;
+        static _eflash1: u32;
+        static _ssram1: u32;
+        static _esram1: u32;
+        static _simage1: u32;
+        static _eimage1: u32;
+    }
+
+    let sflash1 = &_sflash1 as *const u32 as usize;
+    let eflash1 = &_eflash1 as *const u32 as usize;
+    let ssram1 = &_ssram1 as *const u32 as usize;
+    let esram1 = &_esram1 as *const u32 as usize;
+    let simage1 = &_simage1 as *const u32 as usize;
+    let eimage1 = &_eimage1 as *const u32 as usize;
+
+    let pointers = sflash1 as *const Pointers;
+    let header = pointers.read_volatile();
+
+    if header.magic == !0 {
+        return Err(ImageError::Erased);
+    }
+
+    if header.magic != MAGIC {
+        return Err(ImageError::BadMagic);
+    }
+
+    if header.version != VERSION {
+        return Err(ImageError::BadVersion(header.version));
+    }
+
+    // NOTE both linker scripts set the build ID to 0, a value `lpc541xx-update-build-id` never
+    // writes; otherwise two unstamped images would match
+    let build_id = build_id();
+    if build_id == 0 || header.build_id == 0 {
+        return Err(ImageError::Unstamped);
+    }
+
+    // running the two images together would corrupt the `#[shared]` variables
+    if header.build_id != build_id {
+        return Err(ImageError::BuildIdMismatch);
+    }
+
+    // the image is copied from FLASH1, right after the header, into `start..end`
+    let (start, end) = (header.start, header.end);
+    let load = pointers.add(1) as usize;
+    let in_range = start % 4 == 0
+        && end % 4 == 0
+        && simage1 <= start
+        && start < end
+        && end <= eimage1
+        && end - start <= eflash1 - load;
+
+    if !in_range {
+        return Err(ImageError::OutOfRange);
+    }
+
+    if crc32(load as *const u8, end - start) != header.crc {
+        return Err(ImageError::BadCrc);
+    }
+
+    // initial stack pointer and reset handler, read from Flash
+    let vectors = load as *const u32;
+    let stack = vectors.read_volatile() as usize;
+    let entry = vectors.add(1).read_volatile() as usize;
+
+    // NOTE the entry point is a Thumb address so its LSB is set
+    if stack < ssram1 || stack > esram1 || entry & 1 == 0 || entry < start || entry >= end {
+        return Err(ImageError::OutOfRange);
+    }
+
+    Ok(pointers)
+}
+
+// Returns the build ID of this image
+fn build_id() -> u32 {
+    extern "C" {
+        // NOTE patched after linking so it must be read from memory
+        static _build_id: u32;
+    }
+
+    unsafe { (&_build_id as *const u32).read_volatile() }
+}
+
+// Computes the CRC-32 of `len` bytes starting at `start` using the CRC engine
+unsafe fn crc32(start: *const u8, len: usize) -> u32 {
+    const SYSCON_AHBCLKCTRLSET0: *mut u32 = 0x4000_0220 as *mut u32;
+
+    const CRC_BASE: usize = 0x4009_5000;
+    const CRC_MODE: *mut u32 = CRC_BASE as *mut u32;
+    const CRC_SEED: *mut u32 = (CRC_BASE + 0x4) as *mut u32;
+    const CRC_SUM: *const u32 = (CRC_BASE + 0x8) as *const u32;
+    const CRC_WR_DATA: *mut u8 = (CRC_BASE + 0x8) as *mut u8;
+
+    // enable the CRC engine (21)
+    SYSCON_AHBCLKCTRLSET0.write_volatile(1 << 21);
+
+    // CRC-32 polynomial, bit reversed input, bit reversed and complemented output
+    const CRC_POLY_32: u32 = 0b10;
+    const BIT_RVS_WR: u32 = 1 << 2;
+    const BIT_RVS_SUM: u32 = 1 << 4;
+    const CMPL_SUM: u32 = 1 << 5;
+
+    CRC_MODE.write_volatile(CRC_POLY_32 | BIT_RVS_WR | BIT_RVS_SUM | CMPL_SUM);
+    CRC_SEED.write_volatile(!0);
+
+    // NOTE byte writes so that the bytes are processed in memory order
+    for i in 0..len {
+        CRC_WR_DATA.write_volatile(start.add(i).read_volatile());
+    }
+
+    CRC_SUM.read_volatile()
+}
+
+// Boots core #1 for the first time
+//
+// NOTE this must run after SRAM1 has been enabled and after the `.shared` variables and this
+// core's `static` variables have been initialized. Core #1 is not booted if its image is invalid;
+// `wait_ready` reports the error
+pub(crate) unsafe fn boot() {
+    let (stack, entry) = match init() {
+        Ok(x) => x,
+        Err(_) => return,
+    };
+
+    // ensure that the slave is only active *after* its memory has been initialized
+    atomic::compiler_fence(Ordering::SeqCst);
+
+    SYSCON_CPSTACK.write_volatile(stack);
+    SYSCON_CPBOOT.write_volatile(entry);
+
+    // enable the M0+ clock but hold the core in reset
+    SYSCON_CPUCTRL.write_volatile(CPUCTRL | CM0CLKEN | CM0RSTEN);
+
+    // release the M0+ from reset
+    SYSCON_CPUCTRL.write_volatile(CPUCTRL | CM0CLKEN);
+}
@@ -7,12 +7,26 @@ use core::{
     cmp,
     convert::{Infallible, TryInto},
     fmt, ops,
-    sync::atomic::{self, Ordering},
+    sync::atomic::{self, AtomicBool, AtomicU32, AtomicU8, Ordering},
 };
 
 use bare_metal::Nr;
 use rtfm::{Fraction, Monotonic, MultiCore};
 
+pub use lpc541xx_macros::{entry, ramfunc};
+
+pub mod blog;
+pub mod channel;
+#[cfg(all(master, not(single_core)))]
+pub mod core1;
+pub mod log;
+pub mod mailbox;
+pub mod mutex;
+pub mod pool;
+pub mod rpc;
+pub mod rtt;
+pub mod sync;
+
 const GPIO_BASE: usize = 0x4008_C000;
 const GPIO_SET0: *mut u32 = (GPIO_BASE + 0x2200) as *mut u32;
 const GPIO_SET1: *mut u32 = (GPIO_BASE + 0x2204) as *mut u32;
@@ -104,6 +118,40 @@ pub const NVIC_PRIO_BITS: u8 = 2;
 
 const MAILBOX_BASE: usize = 0x4008_B000;
 
+// mailbox bit reserved by this crate; interrupt #30 doesn't exist
+// - it wakes up a core that's sleeping in one of the `sync` primitives
+// - on core #0, it also forwards the M4-only interrupts (32-37)
+const MAILBOX_NOTIFY: u32 = 1 << 30;
+
+// State shared between the two cores
+//
+// NOTE both cores must agree on the layout of this struct. It's placed at the start of the
+// `.shared` section by both linker scripts (`core0.x` and `core1.x`)
+#[repr(C)]
+struct Shared {
+    // number of times the Cortex-M0+ has pended each of the M4-only interrupts (32-37)
+    xpend_high: [AtomicU8; 6],
+    // mailbox bits of each core that carry payload instead of interrupt requests
+    payload_mask: [AtomicU32; 2],
+    // set by the Cortex-M0+ right before it calls `main`
+    core1_ready: AtomicBool,
+}
+
+#[link_section = ".shared.lpc541xx"]
+#[used]
+static SHARED: Shared = Shared {
+    xpend_high: [
+        AtomicU8::new(0),
+        AtomicU8::new(0),
+        AtomicU8::new(0),
+        AtomicU8::new(0),
+        AtomicU8::new(0),
+        AtomicU8::new(0),
+    ],
+    payload_mask: [AtomicU32::new(0), AtomicU32::new(0)],
+    core1_ready: AtomicBool::new(false),
+};
+
 pub struct CTIMER0;
 
 const CTIMER0_BASE: usize = 0x40008000;
@@ -127,6 +175,8 @@ impl Monotonic for CTIMER0 {
 
     /// Resets the counter to *zero*
     unsafe fn reset() {
+        // NOTE the counter may already be running if a timeout was used before `init`
+        CTIMER0_TCR.write_volatile(0b10); // hold in reset
         CTIMER0_TCR.write_volatile(0b01); // release from reset
     }
 
@@ -137,6 +187,56 @@ impl Monotonic for CTIMER0 {
 
 impl MultiCore for CTIMER0 {}
 
+/// Cycle counter based `Monotonic` timer of core #0
+///
+/// Unlike `CTIMER0` this timer belongs to core #0, so it can't be used to `schedule` tasks across
+/// cores, but it doesn't use up a CTIMER. It counts processor cycles in the DWT of the Cortex-M4F,
+/// which RTFM's timer queue doesn't use, and wraps around after 2^32 cycles, like `CTIMER0`.
+///
+/// NOTE there's no per-core SysTick based `Monotonic` because RTFM's timer queue already drives the
+/// SysTick of each core that schedules tasks. The Cortex-M0+ has no cycle counter either so
+/// `CTIMER0` is the only `Monotonic` of core #1.
+#[cfg(master)]
+pub struct CYCCNT;
+
+#[cfg(master)]
+impl Monotonic for CYCCNT {
+    type Instant = Instant;
+
+    fn ratio() -> Fraction {
+        Fraction {
+            numerator: 1,
+            denominator: 1,
+        }
+    }
+
+    fn now() -> Instant {
+        const DWT_CYCCNT: *const u32 = 0xE000_1004 as *const u32;
+
+        Instant {
+            inner: unsafe { DWT_CYCCNT.read_volatile() } as i32,
+        }
+    }
+
+    /// Resets the counter to *zero*
+    unsafe fn reset() {
+        const DCB_DEMCR: *mut u32 = 0xE000_EDFC as *mut u32;
+        const DWT_CTRL: *mut u32 = 0xE000_1000 as *mut u32;
+        const DWT_CYCCNT: *mut u32 = 0xE000_1004 as *mut u32;
+        const TRCENA: u32 = 1 << 24;
+        const CYCCNTENA: u32 = 1 << 0;
+
+        // the DWT is only accessible when trace is enabled
+        DCB_DEMCR.write_volatile(DCB_DEMCR.read_volatile() | TRCENA);
+        DWT_CYCCNT.write_volatile(0);
+        DWT_CTRL.write_volatile(DWT_CTRL.read_volatile() | CYCCNTENA);
+    }
+
+    fn zero() -> Instant {
+        Instant { inner: 0 }
+    }
+}
+
 #[derive(Clone, Copy, Eq, PartialEq)]
 pub struct Instant {
     pub inner: i32,
@@ -149,6 +249,18 @@ impl Instant {
         }
     }
 
+    // Returns the instant `timeout` from now, starting the CTIMER0 counter if it's still held in
+    // reset
+    pub(crate) fn deadline(timeout: Duration) -> Self {
+        unsafe {
+            if CTIMER0_TCR.read_volatile() & 0b01 == 0 {
+                CTIMER0_TCR.write_volatile(0b01); // release from reset
+            }
+        }
+
+        Self::now() + timeout
+    }
+
     pub fn duration_since(&self, earlier: Instant) -> Duration {
         let diff = self.inner.wrapping_sub(earlier.inner);
         assert!(diff >= 0, "second instant is later than `self`");
@@ -217,63 +329,272 @@ impl TryInto<u32> for Duration {
     }
 }
 
+/// A core of the LPC541xx
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Core {
+    /// Core #0, the Cortex-M4F
+    Cm4 = 0,
+
+    /// Core #1, the Cortex-M0+
+    Cm0Plus = 1,
+}
+
+impl Core {
+    /// Returns the core this code is running on
+    pub fn current() -> Core {
+        if cfg!(master) {
+            Core::Cm4
+        } else {
+            Core::Cm0Plus
+        }
+    }
+
+    /// Returns the other core
+    pub fn other(self) -> Core {
+        match self {
+            Core::Cm4 => Core::Cm0Plus,
+            Core::Cm0Plus => Core::Cm4,
+        }
+    }
+
+    /// Returns `true` if this core is clocked and out of reset
+    pub fn is_running(self) -> bool {
+        match self {
+            // the master can't be stopped
+            Core::Cm4 => true,
+
+            Core::Cm0Plus => {
+                const SYSCON_CPUCTRL: *const u32 = 0x4000_0800 as *const u32;
+                const CM0CLKEN: u32 = 1 << 3;
+                const CM0RSTEN: u32 = 1 << 5;
+
+                let cpuctrl = unsafe { SYSCON_CPUCTRL.read_volatile() };
+                cpuctrl & (CM0CLKEN | CM0RSTEN) == CM0CLKEN
+            }
+        }
+    }
+}
+
+/// An interrupt that belongs to a specific core
+pub unsafe trait CoreInterrupt: Nr {
+    /// The core that services this interrupt
+    const CORE: Core;
+}
+
+unsafe impl CoreInterrupt for Interrupt_0 {
+    const CORE: Core = Core::Cm4;
+}
+
+#[cfg(not(no_cm0plus))]
+unsafe impl CoreInterrupt for Interrupt_1 {
+    const CORE: Core = Core::Cm0Plus;
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum XpendError {
+    /// The destination core is halted or held in reset
+    CoreHalted,
+
+    /// The interrupt can't be forwarded through the mailbox
+    Unsupported,
+}
+
+/// Pends the interrupt `int` on the core that services it
+pub fn try_xpend<I>(int: I) -> Result<(), XpendError>
+where
+    I: CoreInterrupt,
+{
+    xpend_nr(I::CORE, int.nr())
+}
+
+// NOTE used by the code that RTFM generates; `try_xpend` should be used instead
+#[doc(hidden)]
 pub fn xpend(core: u8, int: impl Nr) {
-    // Cortex-M0+
-    const MAILBOX_IRQ0SET: *mut u32 = (MAILBOX_BASE + 0x04) as *mut u32;
+    let core = match core {
+        0 => Core::Cm4,
+        1 => Core::Cm0Plus,
+        _ => panic!("invalid core"),
+    };
+
+    if pend(core, int.nr()).is_err() {
+        panic!("interrupt can't be forwarded");
+    }
+}
+
+fn xpend_nr(core: Core, nr: u8) -> Result<(), XpendError> {
+    if !core.is_running() {
+        return Err(XpendError::CoreHalted);
+    }
+
+    pend(core, nr)
+}
 
-    // Cortex-M4
-    const MAILBOX_IRQ1SET: *mut u32 = (MAILBOX_BASE + 0x14) as *mut u32;
+// An interrupt that's pended on the core at the other end of a channel, a log or an `Rpc` after
+// every update
+#[derive(Clone, Copy)]
+pub(crate) struct Doorbell {
+    core: Core,
+    nr: u8,
+}
 
-    let nr = int.nr();
+impl Doorbell {
+    pub(crate) fn new<I>(int: I) -> Self
+    where
+        I: CoreInterrupt,
+    {
+        Self {
+            core: I::CORE,
+            nr: int.nr(),
+        }
+    }
 
-    assert!(core < 2);
-    assert!(nr < 32);
+    pub(crate) fn ring(self) {
+        // NOTE if the other core is halted the update stays in shared memory
+        let _ = xpend_nr(self.core, self.nr);
+    }
+}
 
-    unsafe {
-        if core == 0 {
-            MAILBOX_IRQ1SET.write_volatile(1 << nr);
-        } else {
-            MAILBOX_IRQ0SET.write_volatile(1 << nr);
+// Ring buffers index their storage with free-running counters; `index % N` only stays continuous
+// when the counters wrap around if `N` divides 2^32
+pub(crate) struct PowerOfTwo<const N: usize>;
+
+impl<const N: usize> PowerOfTwo<N> {
+    // NOTE evaluating this constant fails to compile if `N` is not a power of two
+    pub(crate) const ASSERT: () = assert!(N.is_power_of_two(), "`N` must be a power of two");
+}
+
+fn pend(core: Core, nr: u8) -> Result<(), XpendError> {
+    if core == Core::current() {
+        const NVIC_ISPR: *mut u32 = 0xE000_E200 as *mut u32;
+
+        if nr >= 38 || (core == Core::Cm0Plus && nr >= 32) {
+            return Err(XpendError::Unsupported);
+        }
+
+        unsafe {
+            NVIC_ISPR
+                .add(usize::from(nr / 32))
+                .write_volatile(1 << (nr % 32));
+        }
+    } else if nr < 32 {
+        if mailbox::payload_mask(core) & (1 << nr) != 0 {
+            // the destination core uses this mailbox bit to carry payload
+            return Err(XpendError::Unsupported);
         }
+
+        mailbox_set(core, 1 << nr);
+    } else if core == Core::Cm4 && nr < 38 {
+        if mailbox::payload_mask(core) & MAILBOX_NOTIFY != 0 {
+            return Err(XpendError::Unsupported);
+        }
+
+        // the mailbox register only has room for the first 32 interrupts so the M4-only interrupts
+        // are signaled through `SHARED` instead
+        cortex_m::interrupt::free(|_| {
+            // NOTE(Relaxed) the Cortex-M0+ is the only writer of this counter
+            let count = &SHARED.xpend_high[usize::from(nr - 32)];
+            count.store(
+                count.load(Ordering::Relaxed).wrapping_add(1),
+                Ordering::Release,
+            );
+        });
+
+        mailbox_set(core, MAILBOX_NOTIFY);
+    } else {
+        return Err(XpendError::Unsupported);
+    }
+
+    Ok(())
+}
+
+// wakes up `core` if it's sleeping in one of the `sync` primitives
+fn wake(core: Core) {
+    if core != Core::current()
+        && core.is_running()
+        && mailbox::payload_mask(core) & MAILBOX_NOTIFY == 0
+    {
+        mailbox_set(core, MAILBOX_NOTIFY);
     }
 }
 
+// Cortex-M0+
+const MAILBOX_IRQ0: *mut u32 = MAILBOX_BASE as *mut u32;
+
+// Cortex-M4
+const MAILBOX_IRQ1: *mut u32 = (MAILBOX_BASE + 0x10) as *mut u32;
+
+fn mailbox_irq(core: Core) -> *mut u32 {
+    match core {
+        Core::Cm4 => MAILBOX_IRQ1,
+        Core::Cm0Plus => MAILBOX_IRQ0,
+    }
+}
+
+fn mailbox_read(core: Core) -> u32 {
+    unsafe { mailbox_irq(core).read_volatile() }
+}
+
+fn mailbox_set(core: Core, mask: u32) {
+    // IRQ0SET / IRQ1SET
+    unsafe { mailbox_irq(core).add(1).write_volatile(mask) }
+}
+
+fn mailbox_clear(core: Core, mask: u32) {
+    // IRQ0CLR / IRQ1CLR
+    unsafe { mailbox_irq(core).add(2).write_volatile(mask) }
+}
+
 // forward interrupts
 #[no_mangle]
 extern "C" fn MAILBOX() {
-    unsafe {
-        const NVIC_ISPR: *mut u32 = 0xE000_E200 as *mut u32;
+    const NVIC_ISPR: *mut u32 = 0xE000_E200 as *mut u32;
 
-        match () {
-            #[cfg(master)]
-            () => {
-                // Cortex-M4
-                const MAILBOX_IRQ1: *const u32 = (MAILBOX_BASE + 0x10) as *const u32;
-                const MAILBOX_IRQ1CLR: *mut u32 = (MAILBOX_BASE + 0x18) as *mut u32;
+    let core = Core::current();
+    let mask = mailbox_read(core);
+    // NOTE clear the mask *before* acting on it so that no request or payload is missed
+    mailbox_clear(core, mask);
 
-                let mask = MAILBOX_IRQ1.read_volatile();
-                NVIC_ISPR.write_volatile(mask);
-                MAILBOX_IRQ1CLR.write_volatile(mask);
-            }
+    let payload = mask & mailbox::payload_mask(core);
+    let mask = mask & !payload;
 
-            #[cfg(not(master))]
-            () => {
-                // Cortex-M0+
-                const MAILBOX_IRQ0: *const u32 = MAILBOX_BASE as *const u32;
-                const MAILBOX_IRQ0CLR: *mut u32 = (MAILBOX_BASE + 0x8) as *mut u32;
+    unsafe {
+        NVIC_ISPR.write_volatile(mask & !MAILBOX_NOTIFY);
 
-                let mask = MAILBOX_IRQ0.read_volatile();
-                NVIC_ISPR.write_volatile(mask);
-                MAILBOX_IRQ0CLR.write_volatile(mask);
+        #[cfg(master)]
+        {
+            if mask & MAILBOX_NOTIFY != 0 {
+                // last seen value of the `xpend_high` counters
+                static mut SEEN: [u8; 6] = [0; 6];
+
+                let mut high = 0;
+                for (i, (count, seen)) in SHARED.xpend_high.iter().zip(&mut SEEN).enumerate() {
+                    let count = count.load(Ordering::Acquire);
+
+                    if count != *seen {
+                        *seen = count;
+                        high |= 1 << i;
+                    }
+                }
+
+                NVIC_ISPR.add(1).write_volatile(high);
             }
         }
     }
+
+    if payload != 0 {
+        mailbox::deliver(payload);
+    }
 }
 
+// The common entry point, `_start`. It's only part of the image of core #0, which contains the
+// vector table both cores boot from, but it's assembled as ARMv6-M code (see `.arch` in `start.s`)
+#[cfg(master)]
+core::arch::global_asm!(include_str!("../start.s"));
+
 // This is the pseudo-Rust version of the common entry point, executed by both cores.
 //
 // Because this requires a custom calling convention this is actually written in assembly and can
-// be found in the `asm.s` file
+// be found in the `start.s` file
 #[cfg(unused)]
 #[no_mangle]
 pub unsafe extern "C" fn _start() -> ! {
@@ -330,7 +651,7 @@ pub unsafe extern "C" fn start() -> ! {
             // enable CTIMER0
             SYSCON_AHBCLKCTRLSET1.write_volatile(1 << 26);
 
-            // held the CTIMER0 counter in reset
+            // held the CTIMER0 counter in reset; it's started by RTFM or by the first timeout
             CTIMER0_TCR.write_volatile(0b10);
 
             // configure LED pins
@@ -348,44 +669,21 @@ pub unsafe extern "C" fn start() -> ! {
             // SRAM2 must be enabled before the `.shared` section is initialized
             atomic::compiler_fence(Ordering::SeqCst);
 
-            // initialize slave memory
-            const POINTERS: *const [usize; 2] = 0x0003_8000 as *const _;
-
-            let pointers = POINTERS.read();
-
-            let _sslave = pointers[0] as *mut u32;
-            let _eslave = pointers[1] as *mut u32;
-            let _sislave = POINTERS.add(1) as *const u32;
-            r0::init_data(_sslave, _eslave, _sislave);
-
             // initialize `.shared` variables
             r0::init_data(&mut _sshared, &mut _eshared, &_sishared);
-
-            // ensure that the slave is only active *after* shared variables and its memory have
-            // been initialized
-            atomic::compiler_fence(Ordering::SeqCst);
-
-            const SYSCON_CPBOOT: *mut u32 = 0x4000_0804 as *mut u32;
-            const SYSCON_CPSTACK: *mut u32 = 0x4000_0808 as *mut u32;
-            const SYSCON_CPUCTRL: *mut u32 = 0x4000_0800 as *mut u32;
-
-            SYSCON_CPSTACK.write_volatile(_sslave.read());
-            SYSCON_CPBOOT.write_volatile(_sslave.add(1).read());
-
-            // enable the M0+ clock but hold the core in reset
-            SYSCON_CPUCTRL.write_volatile(0xc0c4_806d);
-
-            // release the M0+ from reset
-            SYSCON_CPUCTRL.write_volatile(0xc0c4_804d);
         }
 
         #[cfg(not(master))]
         () => {
             const SCB_VTOR: *mut u32 = 0xe000_ed08 as *mut u32;
 
+            extern "C" {
+                static _svectors: u32;
+            }
+
             // after reset the slave uses 0x0 as the start of the vector table
             // this needs to be updated to use the right address
-            SCB_VTOR.write_volatile(start as u32 & !(0xffff));
+            SCB_VTOR.write_volatile(&_svectors as *const u32 as u32);
         }
     }
 
@@ -404,16 +702,27 @@ pub unsafe extern "C" fn start() -> ! {
         static mut _sdata: u32;
         static mut _edata: u32;
         static _sidata: u32;
+
+        static mut _sramfunc: u32;
+        static mut _eramfunc: u32;
+        static _siramfunc: u32;
     }
 
-    // initialize .bss and .data
+    // initialize .bss, .data and .ramfunc
     r0::zero_bss(&mut _sbss, &mut _ebss);
     #[cfg(master)]
     r0::init_data(&mut _sdata, &mut _edata, &_sidata);
+    #[cfg(master)]
+    r0::init_data(&mut _sramfunc, &mut _eramfunc, &_siramfunc);
 
     // do not run `main` before the `static` variables have been initialized
     atomic::compiler_fence(Ordering::SeqCst);
 
+    // NOTE the slave is booted *after* the shared variables have been initialized; the outcome of
+    // validating its image is stored in a `static` variable of the master
+    #[cfg(all(master, not(single_core)))]
+    core1::boot();
+
     extern "Rust" {
         // user program entry point
         fn main() -> !;
@@ -438,7 +747,13 @@ pub unsafe extern "C" fn start() -> ! {
         }
 
         #[cfg(not(master))]
-        () => main(),
+        () => {
+            // tell the master that this core has booted
+            SHARED.core1_ready.store(true, Ordering::Release);
+            wake(Core::Cm4);
+
+            main()
+        }
     }
 }
 
@@ -510,17 +825,12 @@ pub enum Interrupt_0 {
     USB = 28,
     RTC = 29,
     MAILBOX = 31,
-    #[cfg(master)]
+    // NOTE these can be pended by the Cortex-M0+ too, on parts that have one; see `MAILBOX`
     PIN_INT4 = 32,
-    #[cfg(master)]
     PIN_INT5 = 33,
-    #[cfg(master)]
     PIN_INT6 = 34,
-    #[cfg(master)]
     PIN_INT7 = 35,
-    #[cfg(master)]
     CTIMER2 = 36,
-    #[cfg(master)]
     CTIMER4 = 37,
 }
 
@@ -530,6 +840,8 @@ unsafe impl Nr for Interrupt_0 {
     }
 }
 
+// NOTE the LPC54113 has no Cortex-M0+
+#[cfg(not(no_cm0plus))]
 #[allow(non_camel_case_types)]
 #[derive(Clone, Copy)]
 #[repr(u8)]
@@ -567,12 +879,20 @@ pub enum Interrupt_1 {
     MAILBOX = 31,
 }
 
+#[cfg(not(no_cm0plus))]
 unsafe impl Nr for Interrupt_1 {
     fn nr(&self) -> u8 {
         *self as u8
     }
 }
 
+// single-core RTFM applications refer to the interrupts of the core they run on as `Interrupt`
+#[cfg(master)]
+pub use crate::Interrupt_0 as Interrupt;
+
+#[cfg(not(master))]
+pub use crate::Interrupt_1 as Interrupt;
+
 extern "C" {
     fn NMI();
     fn HardFault();
@@ -0,0 +1,276 @@
+//! Logging from core #1 through core #0's ITM
+//!
+//! The Cortex-M0+ has no ITM so core #1 formats its log records into a `Log` ring buffer that
+//! lives in a `#[shared]` static; core #0 drains the buffer into a sink, e.g. ITM stimulus port 1,
+//! prefixing each record with `[1]`.
+//!
+//! ``` ignore
+//! #[shared]
+//! static LOG: Log<512> = Log::new();
+//!
+//! // core #1
+//! let mut log = LOG.writer().doorbell(Interrupt_0::GINT1);
+//! xprintln!(log, "x = {}", x);
+//!
+//! // core #0, e.g. from the GINT1 handler
+//! LOG.reader().drain_itm(&mut itm.stim[1]);
+//! ```
+
+use core::{
+    cell::UnsafeCell,
+    fmt, slice,
+    sync::atomic::{AtomicU32, AtomicUsize, Ordering},
+};
+
+use crate::{CoreInterrupt, Doorbell, PowerOfTwo};
+
+/// Writes a log record, terminated with a newline, into a `log::Writer`
+///
+/// Usage is the same as `iprintln!`
+#[macro_export]
+macro_rules! xprintln {
+    ($writer:expr) => {
+        $writer.record(format_args!(""))
+    };
+    ($writer:expr, $($arg:tt)*) => {
+        $writer.record(format_args!($($arg)*))
+    };
+}
+
+/// A ring buffer of `N` bytes that holds log records
+///
+/// NOTE a record is either written in full or dropped, when the buffer doesn't have room for it
+///
+/// NOTE `N` must be a power of two
+pub struct Log<const N: usize> {
+    buffer: UnsafeCell<[u8; N]>,
+    // NOTE only written by the `Reader`
+    head: AtomicUsize,
+    // number of dropped records already reported by the `Reader`
+    reported: AtomicU32,
+    // NOTE only written by the `Writer`
+    tail: AtomicUsize,
+    // number of records dropped because the buffer was full
+    dropped: AtomicU32,
+}
+
+unsafe impl<const N: usize> Sync for Log<N> {}
+
+impl<const N: usize> Log<N> {
+    /// Creates an empty log
+    pub const fn new() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = PowerOfTwo::<N>::ASSERT;
+
+        Self {
+            buffer: UnsafeCell::new([0; N]),
+            head: AtomicUsize::new(0),
+            reported: AtomicU32::new(0),
+            tail: AtomicUsize::new(0),
+            dropped: AtomicU32::new(0),
+        }
+    }
+
+    /// Returns the writing end of this log
+    ///
+    /// # Safety
+    ///
+    /// There must be at most one `Writer` for this log at any point in time
+    pub unsafe fn writer(&'static self) -> Writer<N> {
+        Writer {
+            log: self,
+            doorbell: None,
+        }
+    }
+
+    /// Returns the reading end of this log
+    ///
+    /// # Safety
+    ///
+    /// There must be at most one `Reader` for this log at any point in time
+    pub unsafe fn reader(&'static self) -> Reader<N> {
+        Reader { log: self }
+    }
+
+    fn byte(&self, index: usize) -> *mut u8 {
+        unsafe { (self.buffer.get() as *mut u8).add(index % N) }
+    }
+}
+
+/// The writing end of a `Log`
+pub struct Writer<const N: usize> {
+    log: &'static Log<N>,
+    doorbell: Option<Doorbell>,
+}
+
+impl<const N: usize> Writer<N> {
+    /// Pends the interrupt `int` on the reading core after every record
+    pub fn doorbell<I>(self, int: I) -> Self
+    where
+        I: CoreInterrupt,
+    {
+        Self {
+            doorbell: Some(Doorbell::new(int)),
+            ..self
+        }
+    }
+
+    /// Formats `args` into a new record
+    ///
+    /// Returns `false` if the record was dropped because the log is full
+    pub fn record(&mut self, args: fmt::Arguments<'_>) -> bool {
+        self.commit(|record| {
+            fmt::write(record, args)?;
+            record.push(b'\n')
+        })
+    }
+
+    /// Writes the binary `frame`, e.g. a `blog!` frame, into a new record
+    ///
+    /// Returns `false` if the frame was dropped because the log is full
+    ///
+    /// NOTE frames are not newline terminated; use `Reader::drain`, not `Reader::drain_itm`, to
+    /// forward them
+    pub fn frame(&mut self, frame: &[u8]) -> bool {
+        self.commit(|record| frame.iter().try_for_each(|byte| record.push(*byte)))
+    }
+
+    fn commit(&mut self, write: impl FnOnce(&mut Record<N>) -> fmt::Result) -> bool {
+        let log = self.log;
+
+        // NOTE(Relaxed) we are the only writer of `tail`
+        let tail = log.tail.load(Ordering::Relaxed);
+        let mut record = Record {
+            log,
+            head: log.head.load(Ordering::Acquire),
+            cursor: tail,
+        };
+
+        if write(&mut record).is_err() {
+            // NOTE(Relaxed) we are the only writer of `dropped`
+            log.dropped.store(
+                log.dropped.load(Ordering::Relaxed).wrapping_add(1),
+                Ordering::Release,
+            );
+
+            return false;
+        }
+
+        // commit the record
+        log.tail.store(record.cursor, Ordering::Release);
+
+        if let Some(doorbell) = self.doorbell {
+            doorbell.ring();
+        }
+
+        true
+    }
+}
+
+// a record that has not been committed yet
+struct Record<const N: usize> {
+    log: &'static Log<N>,
+    head: usize,
+    cursor: usize,
+}
+
+impl<const N: usize> Record<N> {
+    fn push(&mut self, byte: u8) -> fmt::Result {
+        if self.cursor.wrapping_sub(self.head) == N {
+            return Err(fmt::Error);
+        }
+
+        unsafe { self.log.byte(self.cursor).write_volatile(byte) }
+        self.cursor = self.cursor.wrapping_add(1);
+
+        Ok(())
+    }
+}
+
+impl<const N: usize> fmt::Write for Record<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        s.bytes().try_for_each(|byte| self.push(byte))
+    }
+}
+
+/// The reading end of a `Log`
+pub struct Reader<const N: usize> {
+    log: &'static Log<N>,
+}
+
+impl<const N: usize> Reader<N> {
+    /// Passes all the committed records to `sink`
+    ///
+    /// `sink` is called with chunks of the records; a chunk may span several records and a
+    /// record may be split in two chunks. Each record ends with a newline.
+    pub fn drain(&mut self, mut sink: impl FnMut(&[u8])) {
+        let log = self.log;
+
+        // NOTE(Relaxed) we are the only writer of `head`
+        let head = log.head.load(Ordering::Relaxed);
+        let tail = log.tail.load(Ordering::Acquire);
+
+        if head == tail {
+            return;
+        }
+
+        let start = head % N;
+        let len = tail.wrapping_sub(head);
+        // NOTE the `Writer` may be writing the next record so only the committed bytes are viewed
+        // as slices
+        let chunk =
+            |index: usize, len: usize| unsafe { slice::from_raw_parts(log.byte(index), len) };
+
+        if start + len <= N {
+            sink(chunk(start, len));
+        } else {
+            sink(chunk(start, N - start));
+            sink(chunk(0, start + len - N));
+        }
+
+        log.head.store(tail, Ordering::Release);
+    }
+
+    /// Returns the number of records dropped since the last call to this method
+    pub fn dropped(&mut self) -> u32 {
+        let log = self.log;
+
+        let dropped = log.dropped.load(Ordering::Acquire);
+        // NOTE(Relaxed) we are the only writer of `reported`
+        let reported = log.reported.load(Ordering::Relaxed);
+        log.reported.store(dropped, Ordering::Relaxed);
+
+        dropped.wrapping_sub(reported)
+    }
+
+    /// Writes all the committed records to the ITM stimulus `port`, each prefixed with `[1]`
+    #[cfg(master)]
+    pub fn drain_itm(&mut self, port: &mut cortex_m::peripheral::itm::Stim) {
+        use cortex_m::{iprintln, itm};
+
+        let dropped = self.dropped();
+        if dropped != 0 {
+            iprintln!(port, "[1] ({} records dropped)", dropped);
+        }
+
+        // NOTE `drain` always stops at a record boundary
+        let mut start_of_record = true;
+        self.drain(|mut chunk| {
+            while !chunk.is_empty() {
+                if start_of_record {
+                    itm::write_all(port, b"[1] ");
+                }
+
+                let end = chunk
+                    .iter()
+                    .position(|byte| *byte == b'\n')
+                    .map(|i| i + 1)
+                    .unwrap_or(chunk.len());
+
+                itm::write_all(port, &chunk[..end]);
+                start_of_record = chunk[end - 1] == b'\n';
+                chunk = &chunk[end..];
+            }
+        });
+    }
+}
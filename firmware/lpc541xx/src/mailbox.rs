@@ -0,0 +1,89 @@
+//! Mailbox payload: 32-bit messages between the cores
+//!
+//! By default each bit of a core's mailbox register pends the interrupt with the same number on
+//! that core; this is how RTFM forwards messages between cores. `set_payload_handler` reserves a
+//! subset of those bits, or the whole register, for payload: the reserved bits are not forwarded
+//! as interrupts but handed over to a callback.
+//!
+//! NOTE bit 30 is used to wake up a core that's sleeping in one of the `sync` primitives and, on
+//! core #0, to forward the interrupts 32-37. Reserving it for payload disables both.
+
+use core::sync::atomic::Ordering;
+
+use crate::{Core, SHARED};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// The destination core is halted or held in reset
+    CoreHalted,
+
+    /// The payload is zero or has bits outside the payload mask of the destination core
+    InvalidPayload,
+
+    /// The destination core has not yet handled the previous payload
+    WouldBlock,
+}
+
+// called on every payload that this core receives
+// NOTE each core has its own copy of this variable
+static mut HANDLER: Option<fn(u32)> = None;
+
+/// Reserves the `mask` bits of this core's mailbox register for payload
+///
+/// `handler` will be called from the `MAILBOX` interrupt handler with the payload bits that were
+/// set by the other core. This should be configured *before* the other core starts sending
+/// payloads; a `mask` of `0` disables payload mode.
+pub fn set_payload_handler(mask: u32, handler: fn(u32)) {
+    cortex_m::interrupt::free(|_| unsafe {
+        HANDLER = Some(handler);
+
+        SHARED.payload_mask[Core::current() as usize].store(mask, Ordering::Release);
+    })
+}
+
+/// Sends `payload` to `core`
+///
+/// Returns `Error::WouldBlock` if the destination core has not yet handled the previous payload
+pub fn try_send(core: Core, payload: u32) -> Result<(), Error> {
+    if !core.is_running() {
+        return Err(Error::CoreHalted);
+    }
+
+    let mask = payload_mask(core);
+    if payload == 0 || payload & !mask != 0 {
+        return Err(Error::InvalidPayload);
+    }
+
+    // NOTE interrupts are disabled to not interleave with payloads sent from other contexts
+    cortex_m::interrupt::free(|_| {
+        // the mailbox register ORs in new bits so we must wait until the previous payload has been
+        // cleared
+        if crate::mailbox_read(core) & mask != 0 {
+            return Err(Error::WouldBlock);
+        }
+
+        crate::mailbox_set(core, payload);
+
+        Ok(())
+    })
+}
+
+/// Sends `payload` to `core`, busy waiting while the previous payload is pending
+pub fn send(core: Core, payload: u32) -> Result<(), Error> {
+    loop {
+        match try_send(core, payload) {
+            Err(Error::WouldBlock) => continue,
+            res => break res,
+        }
+    }
+}
+
+pub(crate) fn payload_mask(core: Core) -> u32 {
+    SHARED.payload_mask[core as usize].load(Ordering::Acquire)
+}
+
+pub(crate) fn deliver(payload: u32) {
+    if let Some(handler) = unsafe { HANDLER } {
+        handler(payload)
+    }
+}
@@ -0,0 +1,190 @@
+//! Mutual exclusion between the two cores
+//!
+//! # Sharing memory between the cores
+//!
+//! Cross-core primitives, like `HwMutex` or the ones in the `channel`, `pool` and `sync` modules,
+//! must live in memory that's visible to both cores, that is in `#[shared]` statics.
+//!
+//! # Read-modify-write operations
+//!
+//! The Cortex-M0+ has no compare-and-swap, or swap, instructions. So cross-core primitives either
+//! only use atomic loads and stores or do their read-modify-write operations while holding the
+//! MAILBOX hardware mutex. State that only one core uses is updated with interrupts disabled.
+
+use core::{
+    cell::UnsafeCell,
+    marker::PhantomData,
+    ops,
+    sync::atomic::{self, AtomicBool, Ordering},
+};
+
+use crate::{Duration, Instant};
+
+const MAILBOX_MUTEX: *mut u32 = (crate::MAILBOX_BASE + 0xf8) as *mut u32;
+
+// `true` while this core holds the hardware mutex
+// NOTE each core has its own copy of this variable
+static HELD: AtomicBool = AtomicBool::new(false);
+
+/// Data protected by the MAILBOX hardware mutex
+///
+/// See the [module docs](crate::mutex) for where a `HwMutex` must live
+///
+/// NOTE there's a single hardware mutex so all `HwMutex`es share the same lock. Locking a `HwMutex`
+/// while this core holds any other `HwMutex` returns `Error::WouldDeadlock`.
+pub struct HwMutex<T> {
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T> Sync for HwMutex<T> where T: Send {}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// This core already holds the hardware mutex
+    WouldDeadlock,
+
+    /// The other core holds the hardware mutex
+    WouldBlock,
+
+    /// The other core did not release the hardware mutex in time
+    TimedOut,
+}
+
+impl<T> HwMutex<T> {
+    /// Creates a new mutex
+    pub const fn new(data: T) -> Self {
+        Self {
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// Acquires the mutex, busy waiting while the other core holds it
+    pub fn lock(&self) -> Result<HwMutexGuard<'_, T>, Error> {
+        loop {
+            match acquire() {
+                Err(Error::WouldBlock) => continue,
+                res => break res.map(|_| self.guard()),
+            }
+        }
+    }
+
+    /// Attempts to acquire the mutex without waiting
+    pub fn try_lock(&self) -> Result<HwMutexGuard<'_, T>, Error> {
+        acquire().map(|_| self.guard())
+    }
+
+    /// Acquires the mutex, waiting at most `timeout` for the other core to release it
+    pub fn lock_timeout(&self, timeout: Duration) -> Result<HwMutexGuard<'_, T>, Error> {
+        let deadline = Instant::deadline(timeout);
+
+        loop {
+            match acquire() {
+                Err(Error::WouldBlock) => {
+                    if Instant::now() >= deadline {
+                        break Err(Error::TimedOut);
+                    }
+                }
+                res => break res.map(|_| self.guard()),
+            }
+        }
+    }
+
+    fn guard(&self) -> HwMutexGuard<'_, T> {
+        HwMutexGuard {
+            mutex: self,
+            _not_send: PhantomData,
+        }
+    }
+}
+
+/// Exclusive access to the data protected by a `HwMutex`
+///
+/// The mutex is released when the guard is dropped
+pub struct HwMutexGuard<'a, T> {
+    mutex: &'a HwMutex<T>,
+    // the guard must be dropped on the core that acquired the mutex
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<T> ops::Deref for HwMutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T> ops::DerefMut for HwMutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<T> Drop for HwMutexGuard<'_, T> {
+    fn drop(&mut self) {
+        release()
+    }
+}
+
+// Runs `f` while holding the hardware mutex
+//
+// Interrupts are disabled for the whole critical section so that an interrupt handler on this core
+// can't call `locked` while the mutex is held
+//
+// NOTE if this core already holds the mutex, e.g. because an interrupt handler preempted a task
+// that holds a `HwMutexGuard`, the other core is already locked out so `f` runs right away
+pub(crate) fn locked<R>(f: impl FnOnce() -> R) -> R {
+    cortex_m::interrupt::free(|_| {
+        let held = loop {
+            match acquire() {
+                Ok(()) => break false,
+                Err(Error::WouldBlock) => continue,
+                Err(_) => break true,
+            }
+        };
+
+        let r = f();
+        if !held {
+            release();
+        }
+        r
+    })
+}
+
+// Releases the hardware mutex unless this core holds it
+#[cfg(all(master, not(single_core)))]
+pub(crate) fn release_other() {
+    cortex_m::interrupt::free(|_| {
+        if !HELD.load(Ordering::Relaxed) {
+            unsafe { MAILBOX_MUTEX.write_volatile(1) }
+        }
+    })
+}
+
+// NOTE interrupts are disabled so that `HELD` always reflects the state of the hardware mutex as
+// seen by the tasks that run on this core
+fn acquire() -> Result<(), Error> {
+    cortex_m::interrupt::free(|_| {
+        if HELD.load(Ordering::Relaxed) {
+            return Err(Error::WouldDeadlock);
+        }
+
+        // NOTE reading the register returns `1` if we took the mutex; `0` if it was already taken
+        if unsafe { MAILBOX_MUTEX.read_volatile() } & 1 == 0 {
+            return Err(Error::WouldBlock);
+        }
+
+        HELD.store(true, Ordering::Relaxed);
+        atomic::fence(Ordering::Acquire);
+
+        Ok(())
+    })
+}
+
+fn release() {
+    cortex_m::interrupt::free(|_| {
+        atomic::fence(Ordering::Release);
+        unsafe { MAILBOX_MUTEX.write_volatile(1) }
+        HELD.store(false, Ordering::Relaxed);
+    })
+}
@@ -0,0 +1,175 @@
+//! Fixed-size block allocator for moving buffers between the cores
+//!
+//! A `Pool` that lives in a `#[shared]` static, that is in SRAM2, hands out `Box`es. A `Box` owns
+//! its block so it can be moved to the other core, e.g. through a `Channel`, without copying the
+//! block contents; the block returns to the pool when the `Box` is dropped, on either core.
+//!
+//! To move a `Box` through a channel that only carries 32-bit words, like the mailbox payload, use
+//! `Box::into_raw` on one core and `Pool::from_raw` on the other.
+
+use core::{
+    cell::UnsafeCell,
+    fmt,
+    mem::{self, MaybeUninit},
+    ops,
+    ptr::{self, NonNull},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::mutex;
+
+/// A pool of `N` blocks that can hold a `T` each
+///
+/// NOTE allocating a block takes the MAILBOX hardware mutex; see the [`mutex`] module
+/// docs. Freeing a block doesn't take the mutex.
+pub struct Pool<T, const N: usize> {
+    blocks: UnsafeCell<MaybeUninit<[T; N]>>,
+    // `true` while the block is owned by a `Box`
+    used: [AtomicBool; N],
+}
+
+unsafe impl<T, const N: usize> Sync for Pool<T, N> where T: Send {}
+
+impl<T, const N: usize> Pool<T, N> {
+    /// Creates a pool with all its blocks free
+    pub const fn new() -> Self {
+        const FREE: AtomicBool = AtomicBool::new(false);
+
+        Self {
+            blocks: UnsafeCell::new(MaybeUninit::uninit()),
+            used: [FREE; N],
+        }
+    }
+
+    /// Moves `value` into a free block
+    ///
+    /// Returns back the `value` if all the blocks are in use
+    pub fn alloc(&'static self, value: T) -> Result<Box<T>, T> {
+        debug_assert!(is_shared(self), "`Pool` must live in a `#[shared]` static");
+
+        let index = mutex::locked(|| {
+            let index = self
+                .used
+                .iter()
+                .position(|used| !used.load(Ordering::Acquire))?;
+            self.used[index].store(true, Ordering::Relaxed);
+            Some(index)
+        });
+
+        if let Some(index) = index {
+            let block = self.block(index);
+            unsafe {
+                block.write(value);
+
+                Ok(Box {
+                    block: NonNull::new_unchecked(block),
+                    used: &self.used[index],
+                })
+            }
+        } else {
+            Err(value)
+        }
+    }
+
+    /// Returns the number of free blocks
+    pub fn available(&self) -> usize {
+        self.used
+            .iter()
+            .filter(|used| !used.load(Ordering::Relaxed))
+            .count()
+    }
+
+    /// Recreates a `Box` from a pointer returned by `Box::into_raw`
+    ///
+    /// # Safety
+    ///
+    /// `block` must have been allocated from this pool and `from_raw` must be called at most once
+    /// per `into_raw` call
+    pub unsafe fn from_raw(&'static self, block: NonNull<T>) -> Box<T> {
+        let start = self.block(0) as usize;
+        let offset = (block.as_ptr() as usize).wrapping_sub(start);
+        let index = offset / mem::size_of::<T>().max(1);
+
+        assert!(
+            index < N && offset % mem::size_of::<T>().max(1) == 0,
+            "pointer doesn't point into this pool"
+        );
+
+        Box {
+            block,
+            used: &self.used[index],
+        }
+    }
+
+    fn block(&self, index: usize) -> *mut T {
+        unsafe { (self.blocks.get() as *mut T).add(index) }
+    }
+}
+
+/// A value stored in a `Pool` block
+///
+/// The `Box` is the only handle to the block so only the core that holds it can access the value
+pub struct Box<T>
+where
+    T: 'static,
+{
+    block: NonNull<T>,
+    used: &'static AtomicBool,
+}
+
+unsafe impl<T> Send for Box<T> where T: Send {}
+unsafe impl<T> Sync for Box<T> where T: Sync {}
+
+impl<T> Box<T> {
+    /// Consumes the `Box` returning a pointer to its block
+    ///
+    /// The block is not freed; use `Pool::from_raw` to turn the pointer back into a `Box`
+    pub fn into_raw(b: Self) -> NonNull<T> {
+        let block = b.block;
+        mem::forget(b);
+        block
+    }
+}
+
+impl<T> ops::Deref for Box<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { self.block.as_ref() }
+    }
+}
+
+impl<T> ops::DerefMut for Box<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.block.as_mut() }
+    }
+}
+
+impl<T> fmt::Debug for Box<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        T::fmt(self, f)
+    }
+}
+
+impl<T> Drop for Box<T> {
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(self.block.as_ptr()) }
+
+        // NOTE the block is only written by its owner so freeing it doesn't need the mutex
+        self.used.store(false, Ordering::Release);
+    }
+}
+
+// is `x` in the `.shared` section?
+fn is_shared<T>(x: &T) -> bool {
+    extern "C" {
+        static _sshared: u32;
+        static _eshared: u32;
+    }
+
+    let addr = x as *const T as usize;
+    unsafe { addr >= &_sshared as *const u32 as usize && addr < &_eshared as *const u32 as usize }
+}
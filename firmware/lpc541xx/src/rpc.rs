@@ -0,0 +1,307 @@
+//! Remote procedure calls between the cores
+//!
+//! A `Client` on one core sends requests to a `Server` on the other core through an `Rpc` that
+//! lives in a `#[shared]` static. Each side can ring a doorbell, that is pend an interrupt on the
+//! other core, to signal a new request or a new response.
+//!
+//! `Client::call` busy waits for the response. Alternatively, `Client::request` returns a `Ticket`
+//! that can be stored in an RTFM resource and redeemed with `Client::response` from the task bound
+//! to the client's doorbell interrupt.
+//!
+//! There can be at most one request in flight. Requests are tagged with an ID so that a late
+//! response to a request that timed out is never mistaken for the response to a newer request.
+
+use core::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    ptr,
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
+};
+
+use crate::{CoreInterrupt, Doorbell, Duration, Instant};
+
+/// Request / response slots shared by a `Client` and a `Server`
+pub struct Rpc<Req, Resp> {
+    request: UnsafeCell<MaybeUninit<Req>>,
+    response: UnsafeCell<MaybeUninit<Resp>>,
+
+    // NOTE only written by the `Client`
+    // ID of the last request
+    requested: AtomicU32,
+    // `true` while there's a `Ticket` alive
+    outstanding: AtomicBool,
+    // ID of the last response read by the client
+    claimed: AtomicU32,
+
+    // NOTE only written by the `Server`
+    // ID of the last request taken by the server
+    taken: AtomicU32,
+    // ID of the last request the server responded to
+    responded: AtomicU32,
+}
+
+unsafe impl<Req, Resp> Sync for Rpc<Req, Resp>
+where
+    Req: Send,
+    Resp: Send,
+{
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// The server did not take the previous request in time
+    Busy,
+
+    /// The server did not respond in time
+    TimedOut,
+}
+
+impl<Req, Resp> Rpc<Req, Resp> {
+    /// Creates a new `Rpc` with no request in flight
+    pub const fn new() -> Self {
+        Self {
+            request: UnsafeCell::new(MaybeUninit::uninit()),
+            response: UnsafeCell::new(MaybeUninit::uninit()),
+            requested: AtomicU32::new(0),
+            outstanding: AtomicBool::new(false),
+            claimed: AtomicU32::new(0),
+            taken: AtomicU32::new(0),
+            responded: AtomicU32::new(0),
+        }
+    }
+
+    /// Returns the client side of this `Rpc`
+    ///
+    /// # Safety
+    ///
+    /// There must be at most one `Client` for this `Rpc` at any point in time
+    pub unsafe fn client(&'static self) -> Client<Req, Resp> {
+        Client {
+            rpc: self,
+            doorbell: None,
+        }
+    }
+
+    /// Returns the server side of this `Rpc`
+    ///
+    /// # Safety
+    ///
+    /// There must be at most one `Server` for this `Rpc` at any point in time
+    pub unsafe fn server(&'static self) -> Server<Req, Resp> {
+        Server {
+            rpc: self,
+            doorbell: None,
+        }
+    }
+}
+
+/// The client side of an `Rpc`
+pub struct Client<Req, Resp>
+where
+    Req: 'static,
+    Resp: 'static,
+{
+    rpc: &'static Rpc<Req, Resp>,
+    doorbell: Option<Doorbell>,
+}
+
+/// A request in flight
+///
+/// Dropping the ticket abandons the request; its response will be dropped by the server when it
+/// responds to the next request
+pub struct Ticket {
+    id: u32,
+    outstanding: &'static AtomicBool,
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        self.outstanding.store(false, Ordering::Relaxed);
+    }
+}
+
+impl<Req, Resp> Client<Req, Resp> {
+    /// Pends the interrupt `int` on the server core after every request
+    pub fn doorbell<I>(self, int: I) -> Self
+    where
+        I: CoreInterrupt,
+    {
+        Self {
+            doorbell: Some(Doorbell::new(int)),
+            ..self
+        }
+    }
+
+    /// Sends a request without waiting for the response
+    ///
+    /// Returns back the request if the server has not yet taken the previous request or if the
+    /// `Ticket` of the previous request is still alive
+    pub fn request(&mut self, req: Req) -> Result<Ticket, Req> {
+        let rpc = self.rpc;
+
+        // NOTE(Relaxed) we are the only writer of these two
+        let id = rpc.requested.load(Ordering::Relaxed);
+        if rpc.outstanding.load(Ordering::Relaxed) || rpc.taken.load(Ordering::Acquire) != id {
+            return Err(req);
+        }
+
+        let id = id.wrapping_add(1);
+        unsafe { (*rpc.request.get()).as_mut_ptr().write(req) }
+        rpc.outstanding.store(true, Ordering::Relaxed);
+        rpc.requested.store(id, Ordering::Release);
+
+        if let Some(doorbell) = self.doorbell {
+            doorbell.ring();
+        }
+
+        Ok(Ticket {
+            id,
+            outstanding: &rpc.outstanding,
+        })
+    }
+
+    /// Claims the response to the request identified by `ticket`
+    ///
+    /// Returns back the `ticket` if the server has not yet responded
+    pub fn response(&mut self, ticket: Ticket) -> Result<Resp, Ticket> {
+        let rpc = self.rpc;
+
+        assert!(
+            ptr::eq(ticket.outstanding, &rpc.outstanding),
+            "ticket belongs to a different `Rpc`"
+        );
+
+        if rpc.responded.load(Ordering::Acquire) != ticket.id {
+            return Err(ticket);
+        }
+
+        let resp = unsafe { (*rpc.response.get()).as_ptr().read() };
+        // NOTE the server checks this before it overwrites the response
+        rpc.claimed.store(ticket.id, Ordering::Release);
+        drop(ticket);
+
+        Ok(resp)
+    }
+
+    /// Sends a request and busy waits for its response
+    ///
+    /// The request is abandoned if the response doesn't arrive within `timeout`
+    pub fn call(&mut self, req: Req, timeout: Duration) -> Result<Resp, Error> {
+        let deadline = Instant::deadline(timeout);
+
+        let mut req = req;
+        let mut ticket = loop {
+            match self.request(req) {
+                Ok(ticket) => break ticket,
+                Err(r) => {
+                    if Instant::now() >= deadline {
+                        return Err(Error::Busy);
+                    }
+
+                    req = r;
+                }
+            }
+        };
+
+        loop {
+            match self.response(ticket) {
+                Ok(resp) => break Ok(resp),
+                Err(t) => {
+                    if Instant::now() >= deadline {
+                        break Err(Error::TimedOut);
+                    }
+
+                    ticket = t;
+                }
+            }
+        }
+    }
+}
+
+/// The server side of an `Rpc`
+pub struct Server<Req, Resp>
+where
+    Req: 'static,
+    Resp: 'static,
+{
+    rpc: &'static Rpc<Req, Resp>,
+    doorbell: Option<Doorbell>,
+}
+
+/// Identifies the request the server must respond to next
+#[must_use]
+pub struct RequestId {
+    id: u32,
+}
+
+impl<Req, Resp> Server<Req, Resp> {
+    /// Pends the interrupt `int` on the client core after every response
+    pub fn doorbell<I>(self, int: I) -> Self
+    where
+        I: CoreInterrupt,
+    {
+        Self {
+            doorbell: Some(Doorbell::new(int)),
+            ..self
+        }
+    }
+
+    /// Takes the pending request, if any
+    ///
+    /// Returns `None` if there's no new request or if the previous request has not been responded
+    /// to
+    pub fn take(&mut self) -> Option<(RequestId, Req)> {
+        let rpc = self.rpc;
+
+        // NOTE(Relaxed) we are the only writer of these two
+        let taken = rpc.taken.load(Ordering::Relaxed);
+        if rpc.responded.load(Ordering::Relaxed) != taken {
+            return None;
+        }
+
+        let id = rpc.requested.load(Ordering::Acquire);
+        if id == taken {
+            return None;
+        }
+
+        let req = unsafe { (*rpc.request.get()).as_ptr().read() };
+        rpc.taken.store(id, Ordering::Release);
+
+        Some((RequestId { id }, req))
+    }
+
+    /// Responds to the request identified by `id`
+    pub fn respond(&mut self, id: RequestId, resp: Resp) {
+        let rpc = self.rpc;
+
+        // drop the previous response if the client abandoned it
+        // NOTE(Relaxed) we are the only writer of `responded`
+        // NOTE the client can't read the previous response anymore: it sent a newer request, which
+        // requires that the `Ticket` of the previous request was dropped
+        let previous = rpc.responded.load(Ordering::Relaxed);
+        if rpc.claimed.load(Ordering::Acquire) != previous {
+            unsafe { ptr::drop_in_place((*rpc.response.get()).as_mut_ptr()) }
+        }
+
+        unsafe { (*rpc.response.get()).as_mut_ptr().write(resp) }
+        rpc.responded.store(id.id, Ordering::Release);
+
+        if let Some(doorbell) = self.doorbell {
+            doorbell.ring();
+        }
+    }
+
+    /// Serves the pending request, if any, using `f`
+    ///
+    /// Returns `true` if a request was served
+    pub fn serve(&mut self, f: impl FnOnce(Req) -> Resp) -> bool {
+        if let Some((id, req)) = self.take() {
+            let resp = f(req);
+            self.respond(id, resp);
+
+            true
+        } else {
+            false
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! Real-Time Transfer (RTT) channels
+//!
+//! Each core has its own RTT control block, `_SEGGER_RTT`, in its own RAM: SRAM0 for core #0 and
+//! SRAM1 for core #1. A control block has one up-channel (target to host) and one down-channel
+//! (host to target). The host accesses the channels with plain SWD memory reads and writes so,
+//! unlike the ITM, RTT needs no extra pin and also works on the Cortex-M0+.
+//!
+//! NOTE as there are two control blocks in RAM, host tools that scan the RAM for the control block
+//! must be told its address; it's the address of the `_SEGGER_RTT` symbol in the ELF of each core.
+
+use core::{
+    cmp, fmt, ptr,
+    sync::atomic::{self, AtomicBool, Ordering},
+};
+
+/// Size of the up-channel buffer
+pub const UP_SIZE: usize = 1024;
+
+/// Size of the down-channel buffer
+pub const DOWN_SIZE: usize = 16;
+
+// do nothing if the channel is full
+const MODE_NO_BLOCK_SKIP: u32 = 0;
+
+#[repr(C)]
+struct ControlBlock {
+    id: [u8; 16],
+    max_up_buffers: i32,
+    max_down_buffers: i32,
+    up: Buffer,
+    down: Buffer,
+}
+
+#[repr(C)]
+struct Buffer {
+    name: *const u8,
+    buffer: *mut u8,
+    size: u32,
+    // NOTE up-channel: written by the target; down-channel: written by the host
+    write: u32,
+    // NOTE up-channel: written by the host; down-channel: written by the target
+    read: u32,
+    flags: u32,
+}
+
+// NOTE each core has its own copy of these variables
+#[no_mangle]
+static mut _SEGGER_RTT: ControlBlock = ControlBlock {
+    id: [0; 16],
+    max_up_buffers: 0,
+    max_down_buffers: 0,
+    up: Buffer::empty(),
+    down: Buffer::empty(),
+};
+
+static mut UP: [u8; UP_SIZE] = [0; UP_SIZE];
+static mut DOWN: [u8; DOWN_SIZE] = [0; DOWN_SIZE];
+
+static TAKEN: AtomicBool = AtomicBool::new(false);
+
+impl Buffer {
+    const fn empty() -> Self {
+        Self {
+            name: ptr::null(),
+            buffer: ptr::null_mut(),
+            size: 0,
+            write: 0,
+            read: 0,
+            flags: 0,
+        }
+    }
+}
+
+/// Initializes this core's control block and returns its channels
+///
+/// Returns `None` if the channels have already been taken
+pub fn init() -> Option<(UpChannel, DownChannel)> {
+    // NOTE interrupts are disabled instead of using `swap`; see the `mutex` module docs
+    let taken = cortex_m::interrupt::free(|_| {
+        let taken = TAKEN.load(Ordering::Relaxed);
+        TAKEN.store(true, Ordering::Relaxed);
+        taken
+    });
+
+    if taken {
+        return None;
+    }
+
+    #[cfg(master)]
+    const UP_NAME: &[u8] = b"core0\0";
+    #[cfg(not(master))]
+    const UP_NAME: &[u8] = b"core1\0";
+
+    // NOTE the ID is stored reversed; on core #1 `.rodata` is in RAM, before `_SEGGER_RTT`, and the
+    // host would find a complete copy of the ID there first
+    const DI: &[u8] = b"TTR REGGES";
+
+    unsafe {
+        let cb = &mut *ptr::addr_of_mut!(_SEGGER_RTT);
+
+        cb.max_up_buffers = 1;
+        cb.max_down_buffers = 1;
+        cb.up = Buffer {
+            name: UP_NAME.as_ptr(),
+            buffer: ptr::addr_of_mut!(UP) as *mut u8,
+            size: UP_SIZE as u32,
+            write: 0,
+            read: 0,
+            flags: MODE_NO_BLOCK_SKIP,
+        };
+        cb.down = Buffer {
+            name: b"commands\0".as_ptr(),
+            buffer: ptr::addr_of_mut!(DOWN) as *mut u8,
+            size: DOWN_SIZE as u32,
+            write: 0,
+            read: 0,
+            flags: MODE_NO_BLOCK_SKIP,
+        };
+
+        // NOTE the ID is written back to front, and its first byte last, so that the host doesn't
+        // find a partially initialized control block; the rest of the ID is zeros
+        let (first, rest) = DI.split_last().unwrap();
+        for (i, byte) in rest.iter().enumerate() {
+            ptr::write_volatile(&mut cb.id[DI.len() - 1 - i], *byte);
+        }
+        atomic::fence(Ordering::SeqCst);
+        ptr::write_volatile(&mut cb.id[0], *first);
+    }
+
+    Some((UpChannel { _0: () }, DownChannel { _0: () }))
+}
+
+/// The up-channel (target to host) of this core
+pub struct UpChannel {
+    _0: (),
+}
+
+impl UpChannel {
+    /// Writes as many bytes of `bytes` as there's room for in the channel
+    ///
+    /// Returns the number of bytes written
+    pub fn write(&mut self, bytes: &[u8]) -> usize {
+        unsafe {
+            let up = ptr::addr_of_mut!(_SEGGER_RTT.up);
+
+            let write = ptr::addr_of!((*up).write).read_volatile() as usize;
+            let read = ptr::addr_of!((*up).read).read_volatile() as usize;
+
+            // NOTE one byte is kept empty to tell a full buffer apart from an empty one
+            let free = (read + UP_SIZE - write - 1) % UP_SIZE;
+            let n = cmp::min(free, bytes.len());
+
+            let buffer = ptr::addr_of_mut!(UP) as *mut u8;
+            for (i, byte) in bytes[..n].iter().enumerate() {
+                buffer.add((write + i) % UP_SIZE).write_volatile(*byte);
+            }
+
+            // the data must be in memory before the host sees the new write offset
+            atomic::fence(Ordering::SeqCst);
+            ptr::addr_of_mut!((*up).write).write_volatile(((write + n) % UP_SIZE) as u32);
+
+            n
+        }
+    }
+}
+
+impl fmt::Write for UpChannel {
+    /// NOTE the parts of `s` that don't fit in the channel are discarded
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write(s.as_bytes());
+
+        Ok(())
+    }
+}
+
+/// The down-channel (host to target) of this core
+pub struct DownChannel {
+    _0: (),
+}
+
+impl DownChannel {
+    /// Reads the bytes sent by the host into `buf`
+    ///
+    /// Returns the number of bytes read
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        unsafe {
+            let down = ptr::addr_of_mut!(_SEGGER_RTT.down);
+
+            let write = ptr::addr_of!((*down).write).read_volatile() as usize;
+            let read = ptr::addr_of!((*down).read).read_volatile() as usize;
+
+            let available = (write + DOWN_SIZE - read) % DOWN_SIZE;
+            let n = cmp::min(available, buf.len());
+
+            let buffer = ptr::addr_of!(DOWN) as *const u8;
+            for (i, byte) in buf[..n].iter_mut().enumerate() {
+                *byte = buffer.add((read + i) % DOWN_SIZE).read_volatile();
+            }
+
+            // the data must be read before the host sees the new read offset
+            atomic::fence(Ordering::SeqCst);
+            ptr::addr_of_mut!((*down).read).write_volatile(((read + n) % DOWN_SIZE) as u32);
+
+            n
+        }
+    }
+}
@@ -0,0 +1,375 @@
+//! Synchronization primitives that work across the two cores
+//!
+//! These primitives must be shared between the cores and take the MAILBOX hardware mutex to update
+//! their state; see the [`mutex`] module docs. The mutex is only held with interrupts
+//! disabled so these operations can also be used from interrupt handlers.
+//!
+//! NOTE there's a single hardware mutex so waiting on the other core while this core holds a
+//! `HwMutex` deadlocks: the other core needs the mutex to update a primitive.
+//!
+//! The primitives do not depend on RTFM and can also be used before `init` runs.
+
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use cortex_m::{asm, interrupt};
+
+use crate::{mutex, Core, Duration, Instant};
+
+/// How to wait for a primitive to become available
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Wait {
+    /// Busy wait
+    Spin,
+
+    /// Sleep (WFI) between checks
+    ///
+    /// Updates made by the other core wake this core up through the mailbox. Waits with a timeout
+    /// also use this core's SysTick to wake up at the deadline; if the SysTick is in use, e.g. by
+    /// RTFM's timer queue, they busy wait instead.
+    Sleep,
+}
+
+impl Wait {
+    // waits until `f` returns `Some`
+    fn until<T>(self, mut f: impl FnMut() -> Option<T>) -> T {
+        loop {
+            if let Some(x) = self.poll(None, &mut f) {
+                break x;
+            }
+        }
+    }
+
+    // like `until` but gives up at `deadline`
+    pub(crate) fn until_deadline<T>(
+        self,
+        deadline: Instant,
+        mut f: impl FnMut() -> Option<T>,
+    ) -> Option<T> {
+        loop {
+            if let Some(x) = self.poll(Some(deadline), &mut f) {
+                break Some(x);
+            }
+
+            if Instant::now() >= deadline {
+                // one last check
+                break f();
+            }
+        }
+    }
+
+    // calls `f` once; if that returns `None` and this is `Wait::Sleep`, sleeps until the other core
+    // notifies this core, an interrupt becomes pending or `deadline` is reached
+    fn poll<T>(self, deadline: Option<Instant>, f: &mut impl FnMut() -> Option<T>) -> Option<T> {
+        match self {
+            Wait::Spin => f(),
+
+            // NOTE interrupts are disabled so that a wake up that arrives between the check and the
+            // WFI is not lost; a pending interrupt makes the WFI return immediately
+            Wait::Sleep => interrupt::free(|_| {
+                let x = f();
+
+                if x.is_none() {
+                    match deadline {
+                        None => asm::wfi(),
+
+                        // NOTE if the SysTick is not available this busy waits
+                        Some(deadline) => {
+                            if systick::arm(deadline) {
+                                asm::wfi();
+                                systick::disarm();
+                            }
+                        }
+                    }
+                }
+
+                x
+            }),
+        }
+    }
+
+    fn notify(self) {
+        if self == Wait::Sleep {
+            crate::wake(Core::current().other());
+        }
+    }
+}
+
+/// A group of 32 event flags
+pub struct EventFlags {
+    flags: AtomicU32,
+    wait: Wait,
+}
+
+impl EventFlags {
+    /// Creates a new group with all the flags cleared
+    pub const fn new(wait: Wait) -> Self {
+        Self {
+            flags: AtomicU32::new(0),
+            wait,
+        }
+    }
+
+    /// Returns the current state of the flags
+    pub fn get(&self) -> u32 {
+        self.flags.load(Ordering::Acquire)
+    }
+
+    /// Sets the flags in `mask`
+    pub fn set(&self, mask: u32) {
+        mutex::locked(|| {
+            let flags = self.flags.load(Ordering::Relaxed);
+            self.flags.store(flags | mask, Ordering::Release);
+        });
+
+        self.wait.notify();
+    }
+
+    /// Clears the flags in `mask`
+    pub fn clear(&self, mask: u32) {
+        mutex::locked(|| {
+            let flags = self.flags.load(Ordering::Relaxed);
+            self.flags.store(flags & !mask, Ordering::Release);
+        });
+    }
+
+    /// Waits until any of the flags in `mask` is set
+    ///
+    /// Returns the flags in `mask` that were set; these flags are cleared
+    pub fn wait_any(&self, mask: u32) -> u32 {
+        self.wait.until(|| self.take(mask, |set| set != 0))
+    }
+
+    /// Waits until all the flags in `mask` are set
+    ///
+    /// The flags in `mask` are cleared
+    pub fn wait_all(&self, mask: u32) {
+        self.wait.until(|| self.take(mask, |set| set == mask));
+    }
+
+    // clears and returns the flags in `mask` if `ready` returns `true`
+    fn take(&self, mask: u32, ready: impl Fn(u32) -> bool) -> Option<u32> {
+        // NOTE cheap check that doesn't take the hardware mutex
+        if !ready(self.get() & mask) {
+            return None;
+        }
+
+        mutex::locked(|| {
+            let flags = self.flags.load(Ordering::Acquire);
+            let set = flags & mask;
+
+            if ready(set) {
+                self.flags.store(flags & !mask, Ordering::Relaxed);
+                Some(set)
+            } else {
+                // the other core took the flags
+                None
+            }
+        })
+    }
+}
+
+/// A counting semaphore
+pub struct Semaphore {
+    permits: AtomicU32,
+    wait: Wait,
+}
+
+impl Semaphore {
+    /// Creates a new semaphore with `permits` available
+    pub const fn new(permits: u32, wait: Wait) -> Self {
+        Self {
+            permits: AtomicU32::new(permits),
+            wait,
+        }
+    }
+
+    /// Returns the number of available permits
+    pub fn available(&self) -> u32 {
+        self.permits.load(Ordering::Acquire)
+    }
+
+    /// Returns a permit to the semaphore
+    pub fn release(&self) {
+        mutex::locked(|| {
+            let permits = self.permits.load(Ordering::Relaxed);
+            self.permits
+                .store(permits.wrapping_add(1), Ordering::Release);
+        });
+
+        self.wait.notify();
+    }
+
+    /// Takes a permit without waiting
+    ///
+    /// Returns `false` if no permit is available
+    pub fn try_acquire(&self) -> bool {
+        // NOTE cheap check that doesn't take the hardware mutex
+        if self.available() == 0 {
+            return false;
+        }
+
+        mutex::locked(|| {
+            let permits = self.permits.load(Ordering::Acquire);
+
+            if permits == 0 {
+                // the other core took the last permit
+                false
+            } else {
+                self.permits.store(permits - 1, Ordering::Relaxed);
+                true
+            }
+        })
+    }
+
+    /// Takes a permit, waiting until one is available
+    pub fn acquire(&self) {
+        self.wait
+            .until(|| if self.try_acquire() { Some(()) } else { None })
+    }
+}
+
+/// The waiting task did not make progress in time
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TimedOut;
+
+/// A reusable rendezvous point for the two cores
+///
+/// Each call to `wait` blocks until the other core has also reached the barrier; the two cores go
+/// through the same sequence of phases. The barrier doesn't depend on RTFM so it can be used to
+/// synchronize the cores before and during `init`.
+pub struct Barrier {
+    // NOTE each element is only written by the core with the same index
+    // number of phases this core has arrived at
+    arrived: [AtomicU32; 2],
+    // `true` while this core is waiting for the other core to arrive
+    waiting: [AtomicBool; 2],
+    wait: Wait,
+}
+
+impl Barrier {
+    /// Creates a new barrier
+    pub const fn new(wait: Wait) -> Self {
+        Self {
+            arrived: [AtomicU32::new(0), AtomicU32::new(0)],
+            waiting: [AtomicBool::new(false), AtomicBool::new(false)],
+            wait,
+        }
+    }
+
+    /// Waits until the other core reaches the barrier
+    ///
+    /// Returns the number of the phase that was completed, starting at `1`
+    pub fn wait(&self) -> u32 {
+        let phase = self.arrive();
+        self.wait.until(|| self.leave(phase));
+        phase
+    }
+
+    /// Waits at most `timeout` for the other core to reach the barrier
+    ///
+    /// On timeout this core stays at the barrier: the next `wait` or `wait_timeout` call resumes
+    /// waiting for the same phase instead of starting a new one.
+    pub fn wait_timeout(&self, timeout: Duration) -> Result<u32, TimedOut> {
+        let deadline = Instant::deadline(timeout);
+
+        let phase = self.arrive();
+        self.wait
+            .until_deadline(deadline, || self.leave(phase))
+            .map(|_| phase)
+            .ok_or(TimedOut)
+    }
+
+    // NOTE(Relaxed) this core is the only writer of these two
+    fn arrive(&self) -> u32 {
+        let me = Core::current() as usize;
+
+        let phase = self.arrived[me].load(Ordering::Relaxed);
+        if self.waiting[me].load(Ordering::Relaxed) {
+            // resume a wait that timed out
+            return phase;
+        }
+
+        let phase = phase.wrapping_add(1);
+        self.waiting[me].store(true, Ordering::Relaxed);
+        self.arrived[me].store(phase, Ordering::Release);
+
+        self.wait.notify();
+
+        phase
+    }
+
+    fn leave(&self, phase: u32) -> Option<()> {
+        let me = Core::current() as usize;
+        let other = Core::current().other() as usize;
+
+        // NOTE the other core may already be waiting at the next phase
+        let ahead = self.arrived[other]
+            .load(Ordering::Acquire)
+            .wrapping_sub(phase) as i32;
+        if ahead < 0 {
+            return None;
+        }
+
+        self.waiting[me].store(false, Ordering::Relaxed);
+
+        Some(())
+    }
+}
+
+// Bounds a WFI with this core's SysTick
+//
+// Each core has its own SysTick so, unlike a peripheral timer, its interrupt doesn't reach the
+// other core. The SysTick interrupt is never serviced: it only wakes up the WFI and it's cleared
+// before interrupts are re-enabled.
+mod systick {
+    use core::cmp;
+
+    use crate::Instant;
+
+    const SYST_CSR: *mut u32 = 0xE000_E010 as *mut u32;
+    const SYST_RVR: *mut u32 = 0xE000_E014 as *mut u32;
+    const SYST_CVR: *mut u32 = 0xE000_E018 as *mut u32;
+    const SCB_ICSR: *mut u32 = 0xE000_ED04 as *mut u32;
+
+    const ENABLE: u32 = 1 << 0;
+    const TICKINT: u32 = 1 << 1;
+    const CLKSOURCE: u32 = 1 << 2;
+    const PENDSTCLR: u32 = 1 << 25;
+
+    // NOTE longer waits wake up and re-arm the SysTick
+    const MAX_RELOAD: u32 = 0x00ff_ffff;
+
+    // Arms the SysTick to fire at `deadline`, or earlier
+    //
+    // Returns `false` if the SysTick is in use, e.g. by RTFM's timer queue, or if `deadline` has
+    // already been reached
+    //
+    // NOTE must be called with interrupts disabled and followed by `disarm` before they are
+    // re-enabled
+    pub(super) fn arm(deadline: Instant) -> bool {
+        unsafe {
+            if SYST_CSR.read_volatile() & ENABLE != 0 {
+                return false;
+            }
+
+            // NOTE(ratio) the SysTick and CTIMER0 both count processor clock cycles
+            let remaining = deadline.inner.wrapping_sub(Instant::now().inner);
+            if remaining <= 0 {
+                return false;
+            }
+
+            SYST_RVR.write_volatile(cmp::min(remaining as u32, MAX_RELOAD));
+            SYST_CVR.write_volatile(0);
+            SYST_CSR.write_volatile(CLKSOURCE | TICKINT | ENABLE);
+        }
+
+        true
+    }
+
+    pub(super) fn disarm() {
+        unsafe {
+            SYST_CSR.write_volatile(0);
+            SCB_ICSR.write_volatile(PENDSTCLR);
+        }
+    }
+}
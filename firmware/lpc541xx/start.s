@@ -1,3 +1,6 @@
+  # NOTE both cores run this code out of reset so it must be ARMv6-M compatible
+  .arch armv6s-m
+
   # LLD requires that the section flags are explicitly set here
   .section .text._start, "ax"
   .global _start
@@ -37,3 +40,7 @@ _start:
   ldr   r1, [r1, #4]
   mov   sp, r1
   bx    r0
+
+  # restore the architecture of the Cortex-M4F for the rest of the module-level assembly
+  .arch armv7e-m
+  .fpu fpv4-sp-d16
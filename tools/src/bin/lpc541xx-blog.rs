@@ -0,0 +1,199 @@
+#![deny(rust_2018_compatibility)]
+#![deny(rust_2018_idioms)]
+#![deny(warnings)]
+
+//! Decodes the `blog!` frames of one core
+//!
+//! Usage: `lpc541xx-blog <ELF> [FRAMES]`
+//!
+//! `ELF` is the image of the core that produced the frames. The frames are read from the `FRAMES`
+//! file, e.g. the output of `itmdump` for one stimulus port, or from stdin.
+
+use std::{
+    env,
+    error::Error,
+    fs,
+    io::{self, Read},
+    path::PathBuf,
+    process, str,
+};
+
+use xmas_elf::ElfFile;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = env::args().skip(1);
+
+    let path = if let Some(path) = args.next() {
+        PathBuf::from(path)
+    } else {
+        eprintln!("usage: lpc541xx-blog <ELF> [FRAMES]");
+
+        process::exit(1);
+    };
+
+    let bytes = fs::read(&path)?;
+    let ef = ElfFile::new(&bytes)?;
+
+    let (address, strings) = if let Some(sect) = ef.find_section_by_name(".blog") {
+        (sect.address(), sect.raw_data(&ef))
+    } else {
+        eprintln!("error: no `.blog` section found");
+
+        process::exit(1);
+    };
+
+    let mut frames = vec![];
+    if let Some(path) = args.next() {
+        frames = fs::read(path)?;
+    } else {
+        io::stdin().read_to_end(&mut frames)?;
+    }
+
+    let mut frames = &frames[..];
+    while let Some((&len, rest)) = frames.split_first() {
+        let len = usize::from(len);
+
+        if rest.len() < len {
+            eprintln!("error: truncated frame");
+
+            process::exit(1);
+        }
+
+        let (frame, rest) = rest.split_at(len);
+        frames = rest;
+
+        match decode(frame, address, strings) {
+            Ok(message) => println!("{}", message),
+            Err(e) => eprintln!("error: {}", e),
+        }
+    }
+
+    Ok(())
+}
+
+fn decode(mut frame: &[u8], address: u64, strings: &[u8]) -> Result<String, Box<dyn Error>> {
+    let index = u64::from(leb128(&mut frame)?);
+
+    let start = index
+        .checked_sub(address)
+        .map(|offset| offset as usize)
+        .filter(|offset| *offset < strings.len())
+        .ok_or("format string index out of bounds")?;
+    let end = strings[start..]
+        .iter()
+        .position(|byte| *byte == 0)
+        .ok_or("format string is not NUL terminated")?;
+    let fmt = str::from_utf8(&strings[start..start + end])?;
+
+    let mut message = String::new();
+    let mut chars = fmt.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.as_str().starts_with('{') => {
+                chars.next();
+                message.push('{');
+            }
+
+            '{' => {
+                let rest = chars.as_str();
+                let close = rest.find('}').ok_or("unterminated placeholder")?;
+
+                message.push_str(&arg(&mut frame, &rest[..close])?);
+                chars = rest[close + 1..].chars();
+            }
+
+            '}' if chars.as_str().starts_with('}') => {
+                chars.next();
+                message.push('}');
+            }
+
+            _ => message.push(c),
+        }
+    }
+
+    if !frame.is_empty() {
+        return Err("more arguments than placeholders".into());
+    }
+
+    Ok(message)
+}
+
+// decodes the next argument and formats it according to the placeholder `spec`
+fn arg(frame: &mut &[u8], spec: &str) -> Result<String, Box<dyn Error>> {
+    let (&tag, rest) = frame
+        .split_first()
+        .ok_or("fewer arguments than placeholders")?;
+    *frame = rest;
+
+    Ok(match tag {
+        0 => {
+            let x = leb128(frame)?;
+
+            match spec {
+                ":x" => format!("{:x}", x),
+                ":#x" => format!("{:#x}", x),
+                ":b" => format!("{:b}", x),
+                ":#b" => format!("{:#b}", x),
+                _ => x.to_string(),
+            }
+        }
+
+        1 => {
+            let x = leb128(frame)?;
+
+            (((x >> 1) as i32) ^ -((x & 1) as i32)).to_string()
+        }
+
+        2 => (take(frame, 1)?[0] != 0).to_string(),
+
+        3 => std::char::from_u32(leb128(frame)?)
+            .ok_or("invalid `char`")?
+            .to_string(),
+
+        4 => {
+            let mut bytes = [0; 4];
+            bytes.copy_from_slice(take(frame, 4)?);
+
+            f32::from_bits(u32::from_le_bytes(bytes)).to_string()
+        }
+
+        5 => {
+            let len = leb128(frame)? as usize;
+            let s = str::from_utf8(take(frame, len)?)?;
+
+            if spec == ":?" {
+                format!("{:?}", s)
+            } else {
+                s.to_owned()
+            }
+        }
+
+        _ => return Err(format!("unknown argument tag {}", tag).into()),
+    })
+}
+
+fn leb128(frame: &mut &[u8]) -> Result<u32, Box<dyn Error>> {
+    let mut x = 0;
+
+    for shift in (0..35).step_by(7) {
+        let byte = take(frame, 1)?[0];
+        x |= u32::from(byte & 0x7f) << shift;
+
+        if byte & 0x80 == 0 {
+            return Ok(x);
+        }
+    }
+
+    Err("LEB128 value is too large".into())
+}
+
+fn take<'a>(frame: &mut &'a [u8], n: usize) -> Result<&'a [u8], Box<dyn Error>> {
+    if frame.len() < n {
+        return Err("truncated argument".into());
+    }
+
+    let (head, tail) = frame.split_at(n);
+    *frame = tail;
+
+    Ok(head)
+}
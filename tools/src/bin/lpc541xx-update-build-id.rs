@@ -0,0 +1,128 @@
+#![deny(rust_2018_compatibility)]
+#![deny(rust_2018_idioms)]
+#![deny(warnings)]
+
+//! Writes the build ID, a hash of the layout of the `.shared` section, into the images of both
+//! cores
+//!
+//! Usage: `lpc541xx-update-build-id <ELF>..`
+//!
+//! Core #0 refuses to boot core #1 if their build IDs don't match
+
+use std::{env, error::Error, fs, path::Path, process};
+
+use xmas_elf::{
+    sections::SectionData,
+    symbol_table::{Entry, Type},
+    ElfFile,
+};
+
+// offset of the build ID in the `.pointers` header of core #1
+const POINTERS_OFFSET: usize = 20;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let paths = env::args().skip(1).collect::<Vec<_>>();
+
+    if paths.is_empty() {
+        eprintln!("usage: lpc541xx-update-build-id <ELF>..");
+
+        process::exit(1);
+    }
+
+    for path in paths {
+        update(Path::new(&path))?;
+    }
+
+    Ok(())
+}
+
+fn update(path: &Path) -> Result<(), Box<dyn Error>> {
+    let mut bytes = fs::read(path)?;
+    let ef = ElfFile::new(&bytes)?;
+
+    let shared = if let Some(shared) = ef
+        .section_iter()
+        .position(|sect| sect.get_name(&ef) == Ok(".shared"))
+    {
+        shared
+    } else {
+        eprintln!("error: {}: no `.shared` section found", path.display());
+
+        process::exit(1);
+    };
+
+    // the address and size of every variable in `.shared`
+    let mut layout = vec![];
+    for sect in ef.section_iter() {
+        if let SectionData::SymbolTable32(entries) = sect.get_data(&ef)? {
+            for entry in entries {
+                if usize::from(entry.shndx()) == shared
+                    && entry.get_type()? == Type::Object
+                    && entry.size() != 0
+                {
+                    layout.push((entry.value(), entry.size()));
+                }
+            }
+        }
+    }
+
+    layout.sort();
+    layout.dedup();
+
+    let shared = ef.section_header(shared as u16)?;
+    let mut hash = Fnv1a::new();
+    hash.write(shared.address());
+    hash.write(shared.size());
+    for (address, size) in layout {
+        hash.write(address);
+        hash.write(size);
+    }
+    let build_id = hash.finish();
+
+    // core #0 stores its build ID in `.build_id`; core #1 stores it in its image header
+    let offset = if let Some(sect) = ef.find_section_by_name(".build_id") {
+        sect.offset() as usize
+    } else if let Some(sect) = ef.find_section_by_name(".pointers") {
+        sect.offset() as usize + POINTERS_OFFSET
+    } else {
+        eprintln!(
+            "error: {}: no `.build_id` or `.pointers` section found",
+            path.display()
+        );
+
+        process::exit(1);
+    };
+
+    println!("{}: build ID: {:#010x}", path.display(), build_id);
+
+    bytes[offset..offset + 4].copy_from_slice(&build_id.to_le_bytes());
+    fs::write(path, bytes)?;
+
+    Ok(())
+}
+
+struct Fnv1a {
+    state: u32,
+}
+
+impl Fnv1a {
+    fn new() -> Self {
+        Fnv1a { state: 0x811c_9dc5 }
+    }
+
+    fn write(&mut self, x: u64) {
+        for byte in (x as u32).to_le_bytes().iter() {
+            self.state ^= u32::from(*byte);
+            self.state = self.state.wrapping_mul(0x0100_0193);
+        }
+    }
+
+    // NOTE 0 means "unstamped" to core #0 so it's never used as a build ID
+    fn finish(&self) -> u32 {
+        if self.state == 0 {
+            1
+        } else {
+            self.state
+        }
+    }
+}
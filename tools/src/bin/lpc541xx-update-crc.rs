@@ -0,0 +1,125 @@
+#![deny(rust_2018_compatibility)]
+#![deny(rust_2018_idioms)]
+#![deny(warnings)]
+
+//! Writes the CRC of the core #1 image into its `.pointers` header
+//!
+//! Usage: `lpc541xx-update-crc <ELF>`
+
+use std::{env, error::Error, fs, path::PathBuf, process};
+
+use xmas_elf::{program::Type, sections::SectionData, symbol_table::Entry, ElfFile};
+
+// magic, version, start, end, crc, build ID
+const HEADER_SIZE: u64 = 24;
+const CRC_OFFSET: usize = 16;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let path = PathBuf::from(env::args().nth(1).unwrap());
+    let mut bytes = fs::read(&path)?;
+    let ef = ElfFile::new(&bytes)?;
+
+    let sect = if let Some(sect) = ef.find_section_by_name(".pointers") {
+        sect
+    } else {
+        eprintln!("error: no `.pointers` section found");
+
+        process::exit(1);
+    };
+
+    let header = sect.raw_data(&ef);
+    if sect.size() != HEADER_SIZE {
+        eprintln!("error: `.pointers` has an unexpected size; is this the ELF of core #1?");
+
+        process::exit(1);
+    }
+
+    let word = |i: usize| {
+        let mut word = [0; 4];
+        word.copy_from_slice(&header[4 * i..4 * i + 4]);
+        u64::from(u32::from_le_bytes(word))
+    };
+
+    let eflash1 = if let Some(eflash1) = symbol(&ef, "_eflash1")? {
+        eflash1
+    } else {
+        eprintln!("error: symbol `_eflash1` not found; is this the ELF of core #1?");
+
+        process::exit(1);
+    };
+
+    // the image is loaded right after the header
+    let load = sect.address() + HEADER_SIZE;
+    let (start, end) = (word(2), word(3));
+    if start > end || end - start > eflash1.saturating_sub(load) {
+        eprintln!(
+            "error: the image range {:#010x}..{:#010x} in `.pointers` is invalid or doesn't fit in \
+             FLASH1",
+            start, end
+        );
+
+        process::exit(1);
+    }
+    let len = end - start;
+
+    // reconstruct the contents of Flash from the loadable segments
+    let mut image = vec![0xff; len as usize];
+    for ph in ef.program_iter() {
+        if ph.get_type()? != Type::Load || ph.file_size() == 0 {
+            continue;
+        }
+
+        let (start, size) = (ph.physical_addr(), ph.file_size());
+        let data = &bytes[ph.offset() as usize..(ph.offset() + size) as usize];
+
+        for (addr, byte) in (start..start + size).zip(data) {
+            if addr >= load && addr < load + len {
+                image[(addr - load) as usize] = *byte;
+            }
+        }
+    }
+
+    let crc = crc32(&image);
+    println!("crc: {:#010x} ({} bytes)", crc, len);
+
+    let offset = sect.offset() as usize + CRC_OFFSET;
+    bytes[offset..offset + 4].copy_from_slice(&crc.to_le_bytes());
+
+    fs::write(path, bytes)?;
+
+    Ok(())
+}
+
+// Returns the value of the symbol `name`
+fn symbol(ef: &ElfFile<'_>, name: &str) -> Result<Option<u64>, Box<dyn Error>> {
+    for sect in ef.section_iter() {
+        if let SectionData::SymbolTable32(entries) = sect.get_data(ef)? {
+            for entry in entries {
+                if entry.get_name(ef) == Ok(name) {
+                    return Ok(Some(entry.value()));
+                }
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+// CRC-32 as used by zlib; this is what the CRC engine computes in the mode used by `lpc541xx`
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+
+    for byte in bytes {
+        crc ^= u32::from(*byte);
+
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+
+    !crc
+}